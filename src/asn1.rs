@@ -0,0 +1,123 @@
+// Small helpers for walking DER structures parsed by der_parser
+use der_parser::{parse_der, DerObject};
+
+pub fn der<'a>(input: &'a [u8]) -> Result<DerObject<'a>, String> {
+    match parse_der(input) {
+        Ok((_tail, object)) => Ok(object),
+        Err(_) => Err("DER error".into()),
+    }
+}
+
+pub fn sequence<'a, 'b>(object: &'b DerObject<'a>) -> Result<&'b Vec<DerObject<'a>>, String> {
+    object.as_sequence().map_err(|error| format!("{:?}", error))
+}
+
+pub fn slice<'a>(object: &DerObject<'a>) -> Result<&'a [u8], String> {
+    object.as_slice().map_err(|error| format!("{:?}", error))
+}
+
+/// Dotted string, like certificate.rs compares signature algorithms
+pub fn oid(object: &DerObject) -> Result<String, String> {
+    match object.as_oid() {
+        Ok(oid) => Ok(oid.to_string()),
+        Err(error) => Err(format!("{:?}", error)),
+    }
+}
+
+/// AlgorithmIdentifier: OID then optional parameters
+pub fn algorithm_identifier<'a, 'b>(
+    object: &'b DerObject<'a>,
+) -> Result<(String, Option<&'b DerObject<'a>>), String> {
+    let items = sequence(object)?;
+    if items.is_empty() {
+        return Err("Empty AlgorithmIdentifier".into());
+    }
+    Ok((oid(&items[0])?, items.get(1)))
+}
+
+/// One DER element, for when the encoded bytes themselves are needed,
+/// like a TBSCertificate whose signature gets checked
+#[derive(Debug)]
+pub struct Element<'a> {
+    pub tag: u8,
+    // Tag, length and contents
+    pub raw: &'a [u8],
+    pub contents: &'a [u8],
+}
+
+/// Split DER bytes into their top-level elements
+pub fn elements<'a>(input: &'a [u8]) -> Result<Vec<Element<'a>>, String> {
+    let mut elements = vec![];
+    let mut rest = input;
+    while !rest.is_empty() {
+        if rest.len() < 2 {
+            return Err("Truncated DER".into());
+        }
+        let (header, length) = match rest[1] {
+            length @ 0..=0x7f => (2, length as usize),
+            0x81..=0x84 => {
+                let count = (rest[1] & 0x7f) as usize;
+                if rest.len() < 2 + count {
+                    return Err("Truncated DER".into());
+                }
+                let length = rest[2..2 + count]
+                    .iter()
+                    .fold(0usize, |length, &byte| (length << 8) | byte as usize);
+                (2 + count, length)
+            }
+            _ => return Err("Unsupported DER length".into()),
+        };
+        if rest.len() - header < length {
+            return Err("Truncated DER".into());
+        }
+        elements.push(Element {
+            tag: rest[0],
+            raw: &rest[..header + length],
+            contents: &rest[header..header + length],
+        });
+        rest = &rest[header + length..];
+    }
+    Ok(elements)
+}
+
+/// The elements inside a SEQUENCE or SET
+pub fn children<'a>(element: &Element<'a>) -> Result<Vec<Element<'a>>, String> {
+    elements(element.contents)
+}
+
+/// DER string types as text: BMPString is UTF-16, the rest are taken as UTF-8
+pub fn string(element: &Element) -> String {
+    match element.tag {
+        0x1e => {
+            let units: Vec<u16> = element
+                .contents
+                .chunks(2)
+                .map(|pair| (u16::from(pair[0]) << 8) | u16::from(*pair.get(1).unwrap_or(&0)))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(element.contents).into_owned(),
+    }
+}
+
+/// DER integers may need a leading zero byte to be valid ssh mpints
+pub fn mpint(bytes: &[u8]) -> Vec<u8> {
+    let mut mpint = vec![];
+    if !bytes.is_empty() && bytes[0] & 0x80 != 0 {
+        mpint.push(0);
+    }
+    mpint.extend_from_slice(bytes);
+    mpint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mpint;
+
+    #[test]
+    fn test_mpint() {
+        assert_eq!(mpint(&[0x7f, 1]), vec![0x7f, 1]);
+        assert_eq!(mpint(&[0x80, 1]), vec![0, 0x80, 1]);
+        assert_eq!(mpint(&[]), Vec::<u8>::new());
+    }
+}
@@ -0,0 +1,236 @@
+use base64;
+use ssh_key::{peek_algorithm, Algorithm, SshKey};
+use std::path::Path;
+
+/// Name and unquoted value, like ("command", Some("/bin/backup")) or ("no-pty", None)
+pub type Options = Vec<(String, Option<String>)>;
+
+/// One key line of an authorized_keys file
+#[derive(Debug)]
+pub struct Entry {
+    // 1-based, as editors and sshd's logs count them
+    pub line: usize,
+    pub options: Options,
+    pub key: SshKey,
+}
+
+impl Entry {
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options
+            .iter()
+            .any(|(option, _)| option.eq_ignore_ascii_case(name))
+    }
+
+    pub fn option_value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(option, _)| option.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_ref().map(|value| value.as_str()))
+    }
+
+    /// Neither `restrict`, a forced `command=` nor a `from=` source limit,
+    /// so the key gets a full shell from anywhere
+    pub fn is_unrestricted(&self) -> bool {
+        !self.has_option("restrict") && !self.has_option("command") && !self.has_option("from")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AuthorizedKeys {
+    pub entries: Vec<Entry>,
+    // Line number and message for lines that aren't valid entries
+    pub errors: Vec<(usize, String)>,
+}
+
+impl AuthorizedKeys {
+    /// Pairs of line numbers holding the same key, the first occurrence first
+    pub fn duplicates(&self) -> Vec<(usize, usize)> {
+        let mut duplicates = vec![];
+        for (index, entry) in self.entries.iter().enumerate() {
+            let first = self.entries[..index]
+                .iter()
+                .find(|earlier| earlier.key.public_blob == entry.key.public_blob);
+            if let Some(first) = first {
+                duplicates.push((first.line, entry.line));
+            }
+        }
+        duplicates
+    }
+}
+
+/// Files named like authorized_keys or authorized_keys2
+pub fn is_authorized_keys(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with("authorized_keys"))
+        .unwrap_or(false)
+}
+
+/// The account owning `~/.ssh/authorized_keys` when its home directory
+/// is outside /home, /Users and /root, like /var/lib/jenkins or /srv/git
+pub fn service_account(path: &Path) -> Option<String> {
+    let ssh_dir = path.parent()?;
+    if ssh_dir.file_name()? != ".ssh" {
+        return None;
+    }
+    let home = ssh_dir.parent()?;
+    if home == Path::new("/root") {
+        return None;
+    }
+    match home.parent() {
+        Some(parent) if parent == Path::new("/home") || parent == Path::new("/Users") => None,
+        _ => home
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned()),
+    }
+}
+
+/// DSA and RSA keys under 2048 bits
+pub fn is_weak(key: &SshKey) -> bool {
+    match key.algorithm {
+        Algorithm::Dsa(_) => true,
+        Algorithm::Rsa(ref modulus) => modulus.len() < 2048 / 8,
+        _ => false,
+    }
+}
+
+fn is_key_type(word: &str) -> bool {
+    word.starts_with("ssh-") || word.starts_with("ecdsa-") || word.starts_with("sk-")
+}
+
+// Split the options field at the first whitespace outside double quotes
+fn split_options(line: &str) -> Result<(&str, &str), String> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ' ' | '\t' if !in_quotes => return Ok((&line[..index], line[index..].trim_start())),
+            _ => (),
+        }
+    }
+    Err("Options without a key".into())
+}
+
+// `name` or `name="value"`, separated by commas outside double quotes
+fn parse_options(field: &str) -> Result<Options, String> {
+    let mut options = vec![];
+    let mut name = String::new();
+    let mut value: Option<String> = None;
+    let mut in_quotes = false;
+    let mut characters = field.chars();
+    while let Some(character) = characters.next() {
+        match (character, value.as_mut()) {
+            ('\\', Some(value)) if in_quotes => match characters.next() {
+                Some('"') => value.push('"'),
+                Some(other) => {
+                    value.push('\\');
+                    value.push(other);
+                }
+                None => return Err("Unterminated quote in options".into()),
+            },
+            ('"', Some(_)) => in_quotes = !in_quotes,
+            (_, Some(value)) if in_quotes => value.push(character),
+            (',', _) => {
+                options.push((name.clone(), value.take()));
+                name.clear();
+            }
+            ('=', None) => value = Some(String::new()),
+            (_, Some(value)) => value.push(character),
+            (_, None) => name.push(character),
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quote in options".into());
+    }
+    options.push((name, value));
+    if options.iter().any(|(name, _)| name.is_empty()) {
+        return Err("Empty option".into());
+    }
+    Ok(options)
+}
+
+// `type base64 [comment]`; unlike .pub files the comment is optional
+fn parse_key(text: &str) -> Result<SshKey, String> {
+    let mut fields = text.splitn(3, &[' ', '\t'][..]);
+    let _key_type = fields.next();
+    let payload = match fields.next() {
+        Some(payload) => payload,
+        None => return Err("Missing key".into()),
+    };
+    let key_bytes = base64::decode(payload).map_err(|_| "Invalid Base64".to_string())?;
+    Ok(SshKey {
+        algorithm: peek_algorithm(false, &key_bytes)?,
+        comment: fields
+            .next()
+            .map(|comment| comment.trim().to_owned())
+            .filter(|comment| !comment.is_empty()),
+        is_public: true,
+        public_blob: key_bytes,
+        ..Default::default()
+    })
+}
+
+fn parse_line(line: &str) -> Result<(Options, SshKey), String> {
+    let first_word = line.split_whitespace().next().unwrap_or("");
+    if is_key_type(first_word) {
+        return Ok((vec![], parse_key(line)?));
+    }
+    let (field, key) = split_options(line)?;
+    Ok((parse_options(field)?, parse_key(key)?))
+}
+
+/// Parse every entry of an authorized_keys file, skipping blank lines and comments
+pub fn parse(bytes: &[u8]) -> AuthorizedKeys {
+    let mut authorized_keys: AuthorizedKeys = Default::default();
+    for (index, line) in String::from_utf8_lossy(bytes).lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line) {
+            Ok((options, key)) => authorized_keys.entries.push(Entry {
+                line: index + 1,
+                options,
+                key,
+            }),
+            Err(error) => authorized_keys.errors.push((index + 1, error)),
+        }
+    }
+    authorized_keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_options, service_account};
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(
+            parse_options(r#"restrict,command="echo \"a,b\"",from="10.0.0.0/8,::1",no-pty"#),
+            Ok(vec![
+                ("restrict".into(), None),
+                ("command".into(), Some(r#"echo "a,b""#.into())),
+                ("from".into(), Some("10.0.0.0/8,::1".into())),
+                ("no-pty".into(), None),
+            ])
+        );
+        assert!(parse_options(r#"command="unterminated"#).is_err());
+        assert!(parse_options("no-pty,,restrict").is_err());
+    }
+
+    #[test]
+    fn test_service_account() {
+        let service = |path: &str| service_account(Path::new(path));
+        assert_eq!(
+            service("/var/lib/jenkins/.ssh/authorized_keys"),
+            Some("jenkins".into())
+        );
+        assert_eq!(service("/home/alice/.ssh/authorized_keys"), None);
+        assert_eq!(service("/root/.ssh/authorized_keys"), None);
+        assert_eq!(service("/etc/ssh/authorized_keys"), None);
+    }
+}
@@ -1,22 +1,317 @@
+use asn1::{children, der, elements, oid, string, Element};
 use nom_pem;
+use oids;
+use public_key::subject_public_key_info;
+use ring::signature::{self, VerificationAlgorithm};
+use ssh_key::{significant_bits, Algorithm, SshKey};
 use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
 use time;
-use x509_parser;
+
+/// An X.509 Name, like `C=US, O=Tealeaves, CN=tealeaves.local`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DistinguishedName {
+    // Short attribute name (or dotted OID) and value, in certificate order.
+    // Multi-valued RDNs are flattened.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl DistinguishedName {
+    /// The most specific CN
+    pub fn common_name(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(name, _)| name == "CN")
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for DistinguishedName {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        let attributes: Vec<String> = self
+            .attributes
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        write!(out, "{}", attributes.join(", "))
+    }
+}
+
+/// The subjectAltName entries browsers and TLS clients look at
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeneralName {
+    Dns(String),
+    Ip(IpAddr),
+    Email(String),
+    Uri(String),
+}
+
+impl fmt::Display for GeneralName {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GeneralName::Dns(ref name) => write!(out, "DNS:{}", name),
+            GeneralName::Ip(ref address) => write!(out, "IP:{}", address),
+            GeneralName::Email(ref email) => write!(out, "email:{}", email),
+            GeneralName::Uri(ref uri) => write!(out, "URI:{}", uri),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyUsage {
+    DigitalSignature,
+    NonRepudiation,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+    KeyCertSign,
+    CrlSign,
+    EncipherOnly,
+    DecipherOnly,
+}
+
+// In bit order, digitalSignature is the most significant bit of the first byte
+const KEY_USAGES: [KeyUsage; 9] = [
+    KeyUsage::DigitalSignature,
+    KeyUsage::NonRepudiation,
+    KeyUsage::KeyEncipherment,
+    KeyUsage::DataEncipherment,
+    KeyUsage::KeyAgreement,
+    KeyUsage::KeyCertSign,
+    KeyUsage::CrlSign,
+    KeyUsage::EncipherOnly,
+    KeyUsage::DecipherOnly,
+];
+
+impl KeyUsage {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            KeyUsage::DigitalSignature => "digitalSignature",
+            KeyUsage::NonRepudiation => "nonRepudiation",
+            KeyUsage::KeyEncipherment => "keyEncipherment",
+            KeyUsage::DataEncipherment => "dataEncipherment",
+            KeyUsage::KeyAgreement => "keyAgreement",
+            KeyUsage::KeyCertSign => "keyCertSign",
+            KeyUsage::CrlSign => "cRLSign",
+            KeyUsage::EncipherOnly => "encipherOnly",
+            KeyUsage::DecipherOnly => "decipherOnly",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicConstraints {
+    pub is_ca: bool,
+    pub path_len: Option<u32>,
+}
+
+/// What the subjectPublicKeyInfo holds, including keys `SshKey` can't represent
+#[derive(Clone, Debug, PartialEq)]
+pub enum PublicKey {
+    // Modulus bits and public exponent
+    Rsa(usize, u64),
+    // Curve OID, so unsupported curves can still be named
+    Ec(String),
+    Ed25519,
+    // Bits of p
+    Dsa(usize),
+    // Algorithm OID
+    Unknown(String),
+}
+
+impl PublicKey {
+    /// Problems with the key itself, regardless of who signed it
+    pub fn warnings(&self) -> Vec<String> {
+        match *self {
+            PublicKey::Rsa(bits, _) if bits < 2048 => {
+                vec!["🚨 RSA keys should be 2048 bits or larger".into()]
+            }
+            PublicKey::Ec(ref curve) => match curve.as_str() {
+                "1.2.840.10045.3.1.7" | "1.3.132.0.34" | "1.3.132.0.35" => vec![],
+                // Under 128-bit security
+                "1.2.840.10045.3.1.1" | "1.3.132.0.33" => vec![format!(
+                    "🚨 weak curve {}",
+                    oids::name(curve).unwrap_or(curve)
+                )],
+                _ => vec![format!(
+                    "⚠️ curve {} isn't supported by most TLS clients",
+                    oids::name(curve).unwrap_or(curve)
+                )],
+            },
+            PublicKey::Dsa(_) => vec!["⚠️ dsa keys are considered insecure".into()],
+            _ => vec![],
+        }
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PublicKey::Rsa(bits, exponent) => {
+                write!(out, "RSA {} bits, exponent {}", bits, exponent)
+            }
+            PublicKey::Ec(ref curve) => {
+                write!(out, "EC curve {}", oids::name(curve).unwrap_or(curve))
+            }
+            PublicKey::Ed25519 => write!(out, "Ed25519"),
+            PublicKey::Dsa(bits) => write!(out, "DSA {} bits", bits),
+            PublicKey::Unknown(ref algorithm) => write!(out, "unknown algorithm {}", algorithm),
+        }
+    }
+}
+
+/// RSASSA-PSS-params, with the hash and mask generation function as names
+#[derive(Debug, PartialEq)]
+pub struct PssParameters {
+    pub hash: String,
+    pub mask_generation: String,
+    pub mask_generation_hash: String,
+    pub salt_length: u32,
+}
+
+// What RFC 4055 assumes for anything left out
+impl Default for PssParameters {
+    fn default() -> Self {
+        Self {
+            hash: "sha1".into(),
+            mask_generation: "mgf1".into(),
+            mask_generation_hash: "sha1".into(),
+            salt_length: 20,
+        }
+    }
+}
+
+impl fmt::Display for PssParameters {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            out,
+            "{}, {} with {}, salt length {}",
+            self.hash, self.mask_generation, self.mask_generation_hash, self.salt_length
+        )
+    }
+}
 
 #[derive(Debug)]
 pub struct Certificate {
+    // 1 or 3, as people say it rather than as it's encoded
+    pub version: u8,
+    pub serial: Vec<u8>,
+    pub issuer: DistinguishedName,
+    pub subject: DistinguishedName,
+    pub not_before: time::Tm,
     pub expires: time::Tm,
-    pub subject: String,
     // Using der_parser::oid::Oid for this causes cargo conflicts
     pub algorithm: String,
+    pub subject_alt_names: Vec<GeneralName>,
+    // None without a keyUsage extension, which allows every usage
+    pub key_usage: Option<Vec<KeyUsage>>,
+    // Purpose OIDs, see `oids::name`
+    pub extended_key_usage: Vec<String>,
+    pub basic_constraints: Option<BasicConstraints>,
+    pub subject_key_id: Option<Vec<u8>>,
+    pub authority_key_id: Option<Vec<u8>>,
+    pub public_key: PublicKey,
+    // The same key as a public SshKey, so it can be paired with private keys
+    pub key: SshKey,
+    // The subjectPublicKey BIT STRING contents, as signature checks want them
+    pub subject_public_key: Vec<u8>,
+    // What the issuer signed, and how
+    pub tbs_certificate: Vec<u8>,
+    pub signature: Vec<u8>,
+    // Only for RSA-PSS signatures
+    pub pss: Option<PssParameters>,
+    // Critical extension OIDs this crate doesn't read
+    pub unhandled_critical_extensions: Vec<String>,
+}
+
+pub fn format_date(tm: &time::Tm) -> String {
+    time::strftime("%Y-%m-%d", tm).unwrap_or_else(|_| "?".into())
+}
+
+fn colon_hex(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    hex.join(":")
 }
 
 impl Certificate {
-    pub fn new(subject: String, expires: time::Tm, algorithm: String) -> Self {
+    pub fn new(subject: DistinguishedName, expires: time::Tm, algorithm: String) -> Self {
         Self {
-            expires,
+            version: 1,
+            serial: vec![],
+            issuer: Default::default(),
             subject,
+            not_before: time::empty_tm(),
+            expires,
             algorithm,
+            subject_alt_names: vec![],
+            key_usage: None,
+            extended_key_usage: vec![],
+            basic_constraints: None,
+            subject_key_id: None,
+            authority_key_id: None,
+            public_key: PublicKey::Unknown(String::new()),
+            key: Default::default(),
+            subject_public_key: vec![],
+            tbs_certificate: vec![],
+            signature: vec![],
+            pss: None,
+            unhandled_critical_extensions: vec![],
+        }
+    }
+
+    /// The CN, or the whole subject for certificates without one
+    pub fn name(&self) -> String {
+        match self.subject.common_name() {
+            Some(name) => name.to_owned(),
+            None => self.subject.to_string(),
+        }
+    }
+
+    /// Names `issuer` as its issuer, with matching key ids when both have them
+    pub fn is_issued_by(&self, issuer: &Certificate) -> bool {
+        if self.issuer != issuer.subject {
+            return false;
+        }
+        match (&self.authority_key_id, &issuer.subject_key_id) {
+            (Some(authority), Some(subject)) => authority == subject,
+            _ => true,
+        }
+    }
+
+    /// Check the signature with the public key of `issuer`
+    pub fn verify_signature(&self, issuer: &Certificate) -> Result<(), String> {
+        let algorithm = verification_algorithm(&self.algorithm, &self.pss, &issuer.public_key)?;
+        signature::UnparsedPublicKey::new(algorithm, &issuer.subject_public_key)
+            .verify(&self.tbs_certificate, &self.signature)
+            .map_err(|_| format!("signature doesn't verify with the key of {}", issuer.name()))
+    }
+
+    /// The hash the issuer signed with, None for EdDSA
+    pub fn signature_hash(&self) -> Option<&str> {
+        match self.pss {
+            Some(ref pss) => Some(&pss.hash),
+            None => oids::signature_hash(&self.algorithm),
+        }
+    }
+
+    /// MD2, MD5 or SHA-1, whether with RSA or ECDSA
+    pub fn weak_signature_hash(&self) -> Option<&str> {
+        self.signature_hash()
+            .filter(|&hash| oids::is_weak_hash(hash))
+    }
+
+    /// `key` is the private half of the certified public key
+    pub fn pairs_with(&self, key: &SshKey) -> bool {
+        self.key.is_pair(key)
+    }
+
+    /// The serial without the zero byte that keeps a DER INTEGER positive
+    pub fn serial_number(&self) -> &[u8] {
+        match self.serial.split_first() {
+            Some((0, rest)) if !rest.is_empty() => rest,
+            _ => &self.serial,
         }
     }
 
@@ -24,30 +319,233 @@ impl Certificate {
         time::now_utc() > self.expires
     }
 
-    fn format_expiration(&self) -> String {
-        time::strftime("%Y-%m-%d", &self.expires).unwrap_or_else(|_| "?".into())
+    pub fn is_self_issued(&self) -> bool {
+        self.issuer == self.subject
+    }
+
+    pub fn is_ca(&self) -> bool {
+        match self.basic_constraints {
+            Some(ref constraints) => constraints.is_ca,
+            None => false,
+        }
+    }
+
+    /// The DNS and IP subjectAltNames, or the CN for certificates without any
+    pub fn host_names(&self) -> Vec<String> {
+        let hosts: Vec<String> = self
+            .subject_alt_names
+            .iter()
+            .filter_map(|name| match *name {
+                GeneralName::Dns(ref name) => Some(name.clone()),
+                GeneralName::Ip(ref address) => Some(address.to_string()),
+                _ => None,
+            })
+            .collect();
+        if !hosts.is_empty() {
+            return hosts;
+        }
+        self.subject
+            .common_name()
+            .map(|name| vec![name.to_owned()])
+            .unwrap_or_default()
     }
 }
 
 impl fmt::Display for Certificate {
     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
         let mut output = String::new();
-        let host = self.subject.rsplit('=').nth(0).unwrap_or("?");
-        output.push_str(&format!("x509 TLS Certificate (host {})", host));
+        let hosts = self.host_names();
+        match hosts.len() {
+            0 => output.push_str("x509 TLS Certificate (host ?)"),
+            1 => output.push_str(&format!("x509 TLS Certificate (host {})", hosts[0])),
+            _ => output.push_str(&format!(
+                "x509 TLS Certificate (hosts {})",
+                hosts.join(", ")
+            )),
+        }
         if self.is_expired() {
             output.push_str("\n\t🚨 expired ");
         } else {
             output.push_str("\n\t✓ expires ");
         }
-        output.push_str(&self.format_expiration().to_string());
-        // http://www.alvestrand.no/objectid/1.2.840.113549.1.1.5.html
-        if self.algorithm == "1.2.840.113549.1.1.5" {
-            output.push_str("\n\t🚨 insecure SHA1 signature algorithm");
+        output.push_str(&format_date(&self.expires));
+        if let Some(hash) = self.weak_signature_hash() {
+            output.push_str(&format!(
+                "\n\t🚨 insecure {} signature algorithm",
+                hash.to_uppercase()
+            ));
+        }
+        for warning in self.public_key.warnings() {
+            output.push_str(&format!("\n\t{}", warning));
+        }
+        for extension in &self.unhandled_critical_extensions {
+            output.push_str(&format!(
+                "\n\t⚠️ critical extension {} isn't checked here",
+                oids::describe(extension)
+            ));
+        }
+        if self.subject_alt_names.is_empty() && !self.is_ca() {
+            output.push_str("\n\t⚠️ no subjectAltName, TLS clients ignore the CN");
+        }
+        output.push_str(&format!("\n\tsubject: {}", self.subject));
+        if self.is_self_issued() {
+            output.push_str("\n\tissuer: self-issued");
+        } else {
+            output.push_str(&format!("\n\tissuer: {}", self.issuer));
+        }
+        output.push_str(&format!(
+            "\n\tx509 v{}, serial {}, valid from {}",
+            self.version,
+            colon_hex(self.serial_number()),
+            format_date(&self.not_before)
+        ));
+        output.push_str(&format!("\n\tpublic key: {}", self.public_key));
+        output.push_str(&format!(
+            "\n\tsigned with {}",
+            oids::describe(&self.algorithm)
+        ));
+        if let Some(ref pss) = self.pss {
+            output.push_str(&format!(" ({})", pss));
+        }
+        let other_names: Vec<String> = self
+            .subject_alt_names
+            .iter()
+            .filter(|name| matches!(**name, GeneralName::Email(_) | GeneralName::Uri(_)))
+            .map(|name| name.to_string())
+            .collect();
+        if !other_names.is_empty() {
+            output.push_str(&format!("\n\talso names {}", other_names.join(", ")));
+        }
+        if let Some(ref key_usage) = self.key_usage {
+            let usages: Vec<&str> = key_usage.iter().map(|usage| usage.as_str()).collect();
+            output.push_str(&format!("\n\tkey usage: {}", usages.join(", ")));
+        }
+        if !self.extended_key_usage.is_empty() {
+            let purposes: Vec<&str> = self
+                .extended_key_usage
+                .iter()
+                .map(|oid| oids::name(oid).unwrap_or(oid))
+                .collect();
+            output.push_str(&format!("\n\textended key usage: {}", purposes.join(", ")));
+        }
+        match self.basic_constraints {
+            Some(BasicConstraints {
+                is_ca: true,
+                path_len: Some(path_len),
+            }) => output.push_str(&format!("\n\tCA, path length {}", path_len)),
+            Some(BasicConstraints { is_ca: true, .. }) => output.push_str("\n\tCA"),
+            _ => (),
+        }
+        if let Some(ref id) = self.subject_key_id {
+            output.push_str(&format!("\n\tsubject key id {}", colon_hex(id)));
+        }
+        if let Some(ref id) = self.authority_key_id {
+            output.push_str(&format!("\n\tauthority key id {}", colon_hex(id)));
         }
         write!(out, "{}", output)
     }
 }
 
+/// Certificates from the leaf up, as indexes into `Bundle::certificates`
+#[derive(Debug, PartialEq)]
+pub struct Chain {
+    pub indexes: Vec<usize>,
+    // Issuer of the last certificate when it isn't self-issued
+    // and isn't in the bundle
+    pub missing_issuer: Option<DistinguishedName>,
+}
+
+impl Chain {
+    /// Each certificate comes right before its issuer, as TLS servers send them
+    pub fn is_in_order(&self) -> bool {
+        self.indexes.windows(2).all(|pair| pair[1] == pair[0] + 1)
+    }
+}
+
+/// Every certificate in a file like fullchain.pem or ca-bundle.crt, in file order
+#[derive(Debug, Default)]
+pub struct Bundle {
+    pub certificates: Vec<Certificate>,
+    // CERTIFICATE blocks that didn't parse, numbered from 1 among all blocks
+    pub errors: Vec<(usize, String)>,
+}
+
+impl Bundle {
+    /// Pairs of indexes with the same issuer and serial, the first occurrence first
+    pub fn duplicates(&self) -> Vec<(usize, usize)> {
+        let mut duplicates = vec![];
+        for (index, certificate) in self.certificates.iter().enumerate() {
+            let first = self.certificates[..index].iter().position(|earlier| {
+                earlier.issuer == certificate.issuer && earlier.serial == certificate.serial
+            });
+            if let Some(first) = first {
+                duplicates.push((first, index));
+            }
+        }
+        duplicates
+    }
+
+    /// Chains from each certificate that issued nothing else in the bundle,
+    /// so a CA bundle of roots is one chain per root. Duplicates are left out.
+    pub fn chains(&self) -> Vec<Chain> {
+        let duplicates: Vec<usize> = self
+            .duplicates()
+            .iter()
+            .map(|&(_, duplicate)| duplicate)
+            .collect();
+        let unique: Vec<usize> = (0..self.certificates.len())
+            .filter(|index| !duplicates.contains(index))
+            .collect();
+        let issuers: Vec<Option<usize>> = self
+            .certificates
+            .iter()
+            .enumerate()
+            .map(|(index, certificate)| {
+                if certificate.is_self_issued() {
+                    return None;
+                }
+                unique.iter().cloned().find(|&other| {
+                    other != index && certificate.is_issued_by(&self.certificates[other])
+                })
+            })
+            .collect();
+        let mut visited = vec![false; self.certificates.len()];
+        let mut chains = vec![];
+        // Then whatever is left in a loop of cross-signed certificates
+        let starts = unique
+            .iter()
+            .filter(|&&index| !unique.iter().any(|&other| issuers[other] == Some(index)))
+            .chain(unique.iter());
+        for &start in starts {
+            if visited[start] {
+                continue;
+            }
+            let mut indexes = vec![];
+            let mut next = Some(start);
+            while let Some(index) = next {
+                if indexes.contains(&index) {
+                    break;
+                }
+                indexes.push(index);
+                visited[index] = true;
+                next = issuers[index];
+            }
+            let last = indexes[indexes.len() - 1];
+            let certificate = &self.certificates[last];
+            let missing_issuer = if certificate.is_self_issued() || issuers[last].is_some() {
+                None
+            } else {
+                Some(certificate.issuer.clone())
+            };
+            chains.push(Chain {
+                indexes,
+                missing_issuer,
+            });
+        }
+        chains
+    }
+}
+
 fn strerr<T>(error: T) -> String
 where
     T: fmt::Debug,
@@ -55,13 +553,426 @@ where
     return format!("{:?}", error);
 }
 
+fn element_oid(element: &Element) -> Result<String, String> {
+    oid(&der(element.raw)?)
+}
+
+fn expect<'a, 'b>(
+    items: &'b [Element<'a>],
+    index: usize,
+    tag: u8,
+) -> Result<&'b Element<'a>, String> {
+    match items.get(index) {
+        Some(element) if element.tag == tag => Ok(element),
+        _ => Err("Invalid certificate".into()),
+    }
+}
+
+// SEQUENCE OF SET OF (type, value)
+fn distinguished_name(element: &Element) -> Result<DistinguishedName, String> {
+    let mut name: DistinguishedName = Default::default();
+    for rdn in children(element)? {
+        for attribute in children(&rdn)? {
+            let parts = children(&attribute)?;
+            if parts.len() != 2 {
+                return Err("Invalid distinguished name".into());
+            }
+            name.attributes
+                .push((oids::describe(&element_oid(&parts[0])?), string(&parts[1])));
+        }
+    }
+    Ok(name)
+}
+
+// UTCTime years 50 to 99 are 1950 to 1999, GeneralizedTime has all four digits
+fn parse_time(element: &Element) -> Result<time::Tm, String> {
+    let text = string(element);
+    let text = text.trim_end_matches('Z');
+    let full = match (element.tag, text.get(..2).map(|year| year.parse::<u32>())) {
+        (0x17, Some(Ok(year))) if year >= 50 => format!("19{}", text),
+        (0x17, Some(Ok(_))) => format!("20{}", text),
+        (0x18, _) => text.to_owned(),
+        _ => return Err(format!("Invalid time {}", text)),
+    };
+    time::strptime(&full, "%Y%m%d%H%M%S").map_err(|_| format!("Invalid time {}", text))
+}
+
+fn subject_alt_names(value: &[u8]) -> Result<Vec<GeneralName>, String> {
+    let mut names = vec![];
+    for name in children(expect(&elements(value)?, 0, 0x30)?)? {
+        let text = || String::from_utf8_lossy(name.contents).into_owned();
+        // Context-specific IMPLICIT tags, other name forms are skipped
+        match name.tag {
+            0x81 => names.push(GeneralName::Email(text())),
+            0x82 => names.push(GeneralName::Dns(text())),
+            0x86 => names.push(GeneralName::Uri(text())),
+            0x87 => match name.contents.len() {
+                4 => {
+                    let c = name.contents;
+                    names.push(GeneralName::Ip(IpAddr::V4(Ipv4Addr::new(
+                        c[0], c[1], c[2], c[3],
+                    ))))
+                }
+                16 => {
+                    let mut octets = [0u8; 16];
+                    octets.copy_from_slice(name.contents);
+                    names.push(GeneralName::Ip(IpAddr::V6(Ipv6Addr::from(octets))))
+                }
+                _ => return Err("Invalid IP address in subjectAltName".into()),
+            },
+            _ => (),
+        }
+    }
+    Ok(names)
+}
+
+fn key_usage(value: &[u8]) -> Result<Vec<KeyUsage>, String> {
+    let bits = expect(&elements(value)?, 0, 0x03)?.contents;
+    // The first byte counts the unused bits
+    let bytes = bits.get(1..).unwrap_or(&[]);
+    Ok(KEY_USAGES
+        .iter()
+        .enumerate()
+        .filter(|&(bit, _)| {
+            bytes
+                .get(bit / 8)
+                .map(|byte| byte & (0x80 >> (bit % 8)) != 0)
+                .unwrap_or(false)
+        })
+        .map(|(_, usage)| *usage)
+        .collect())
+}
+
+fn basic_constraints(value: &[u8]) -> Result<BasicConstraints, String> {
+    let mut constraints = BasicConstraints {
+        is_ca: false,
+        path_len: None,
+    };
+    for item in children(expect(&elements(value)?, 0, 0x30)?)? {
+        match item.tag {
+            0x01 => constraints.is_ca = item.contents.first().map(|&byte| byte != 0) == Some(true),
+            0x02 => {
+                constraints.path_len = Some(
+                    item.contents
+                        .iter()
+                        .fold(0u32, |length, &byte| (length << 8) | u32::from(byte)),
+                )
+            }
+            _ => return Err("Invalid basicConstraints".into()),
+        }
+    }
+    Ok(constraints)
+}
+
+fn unsigned_integer(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0u64, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+/*
+SubjectPublicKeyInfo ::= SEQUENCE {
+    algorithm         AlgorithmIdentifier,
+    subjectPublicKey  BIT STRING
+}
+*/
+fn public_key(element: &Element) -> Result<PublicKey, String> {
+    let parts = children(element)?;
+    let algorithm = children(expect(&parts, 0, 0x30)?)?;
+    let id = element_oid(expect(&algorithm, 0, 0x06)?)?;
+    // The first byte counts the unused bits
+    let key = expect(&parts, 1, 0x03)?.contents.get(1..).unwrap_or(&[]);
+    match id.as_str() {
+        "1.2.840.113549.1.1.1" => {
+            // RSAPublicKey ::= SEQUENCE { modulus, publicExponent }
+            let integers = children(expect(&elements(key)?, 0, 0x30)?)?;
+            let modulus = expect(&integers, 0, 0x02)?.contents;
+            let exponent = expect(&integers, 1, 0x02)?.contents;
+            if exponent.len() > 8 {
+                return Err("Invalid RSA exponent".into());
+            }
+            Ok(PublicKey::Rsa(
+                significant_bits(modulus),
+                unsigned_integer(exponent),
+            ))
+        }
+        // Named curves only, explicit parameters are reported as unknown
+        "1.2.840.10045.2.1" => match algorithm.get(1) {
+            Some(curve) if curve.tag == 0x06 => Ok(PublicKey::Ec(element_oid(curve)?)),
+            _ => Ok(PublicKey::Unknown(id)),
+        },
+        "1.3.101.112" => Ok(PublicKey::Ed25519),
+        "1.2.840.10040.4.1" => {
+            // Dss-Parms ::= SEQUENCE { p, q, g }
+            let parameters = children(expect(&algorithm, 1, 0x30)?)?;
+            Ok(PublicKey::Dsa(significant_bits(
+                expect(&parameters, 0, 0x02)?.contents,
+            )))
+        }
+        _ => Ok(PublicKey::Unknown(id)),
+    }
+}
+
+// The name of the hash in a DER hash AlgorithmIdentifier
+fn hash_name(bytes: &[u8]) -> Result<String, String> {
+    let algorithm = children(expect(&elements(bytes)?, 0, 0x30)?)?;
+    Ok(oids::describe(&element_oid(expect(&algorithm, 0, 0x06)?)?))
+}
+
+/*
+RSASSA-PSS-params ::= SEQUENCE {
+    hashAlgorithm      [0] HashAlgorithm DEFAULT sha1,
+    maskGenAlgorithm   [1] MaskGenAlgorithm DEFAULT mgf1SHA1,
+    saltLength         [2] INTEGER DEFAULT 20,
+    trailerField       [3] TrailerField DEFAULT trailerFieldBC
+}
+*/
+fn pss_parameters(parameters: &Element) -> Result<PssParameters, String> {
+    let mut pss: PssParameters = Default::default();
+    if parameters.tag != 0x30 {
+        return Err("Invalid RSA-PSS parameters".into());
+    }
+    for item in children(parameters)? {
+        match item.tag {
+            0xa0 => pss.hash = hash_name(item.contents)?,
+            0xa1 => {
+                // An AlgorithmIdentifier whose parameters are the MGF1 hash
+                let function = children(expect(&elements(item.contents)?, 0, 0x30)?)?;
+                pss.mask_generation = oids::describe(&element_oid(expect(&function, 0, 0x06)?)?);
+                if let Some(hash) = function.get(1) {
+                    pss.mask_generation_hash = hash_name(hash.raw)?;
+                }
+            }
+            0xa2 => {
+                let length = expect(&elements(item.contents)?, 0, 0x02)?.contents;
+                if length.len() > 4 {
+                    return Err("Invalid RSA-PSS salt length".into());
+                }
+                pss.salt_length = unsigned_integer(length) as u32;
+            }
+            _ => (),
+        }
+    }
+    Ok(pss)
+}
+
+// ring picks the hash and key size limits, the legacy variants allow
+// RSA keys down to 1024 bits, which get their own warning
+fn verification_algorithm(
+    algorithm: &str,
+    pss: &Option<PssParameters>,
+    issuer_key: &PublicKey,
+) -> Result<&'static dyn VerificationAlgorithm, String> {
+    let curve = match *issuer_key {
+        PublicKey::Ec(ref curve) => curve.as_str(),
+        _ => "",
+    };
+    let unsupported = || {
+        Err(format!(
+            "unsupported signature algorithm {} with the issuer's {} key",
+            oids::describe(algorithm),
+            issuer_key
+        ))
+    };
+    Ok(match (algorithm, issuer_key) {
+        ("1.2.840.113549.1.1.5", &PublicKey::Rsa(..)) => {
+            &signature::RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY
+        }
+        ("1.2.840.113549.1.1.11", &PublicKey::Rsa(..)) => {
+            &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY
+        }
+        ("1.2.840.113549.1.1.12", &PublicKey::Rsa(..)) => &signature::RSA_PKCS1_2048_8192_SHA384,
+        ("1.2.840.113549.1.1.13", &PublicKey::Rsa(..)) => {
+            &signature::RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY
+        }
+        // ring wants MGF1 with the same hash and a salt as long as the hash
+        ("1.2.840.113549.1.1.10", &PublicKey::Rsa(..)) => match *pss {
+            Some(ref pss)
+                if pss.mask_generation == "mgf1"
+                    && pss.mask_generation_hash == pss.hash
+                    && oids::hash_length(&pss.hash) == Some(pss.salt_length) =>
+            {
+                match pss.hash.as_str() {
+                    "sha256" => &signature::RSA_PSS_2048_8192_SHA256,
+                    "sha384" => &signature::RSA_PSS_2048_8192_SHA384,
+                    "sha512" => &signature::RSA_PSS_2048_8192_SHA512,
+                    _ => return unsupported(),
+                }
+            }
+            _ => return unsupported(),
+        },
+        ("1.2.840.10045.4.3.2", &PublicKey::Ec(_)) => match curve {
+            "1.2.840.10045.3.1.7" => &signature::ECDSA_P256_SHA256_ASN1,
+            "1.3.132.0.34" => &signature::ECDSA_P384_SHA256_ASN1,
+            _ => return unsupported(),
+        },
+        ("1.2.840.10045.4.3.3", &PublicKey::Ec(_)) => match curve {
+            "1.2.840.10045.3.1.7" => &signature::ECDSA_P256_SHA384_ASN1,
+            "1.3.132.0.34" => &signature::ECDSA_P384_SHA384_ASN1,
+            _ => return unsupported(),
+        },
+        ("1.3.101.112", &PublicKey::Ed25519) => &signature::ED25519,
+        _ => return unsupported(),
+    })
+}
+
+fn add_extension(certificate: &mut Certificate, extension: &Element) -> Result<(), String> {
+    let parts = children(extension)?;
+    let id = element_oid(expect(&parts, 0, 0x06)?)?;
+    // The value is the last part, after the optional critical flag
+    let value = expect(&parts, parts.len() - 1, 0x04)?.contents;
+    match id.as_str() {
+        "2.5.29.14" => {
+            certificate.subject_key_id = Some(expect(&elements(value)?, 0, 0x04)?.contents.to_vec())
+        }
+        "2.5.29.15" => certificate.key_usage = Some(key_usage(value)?),
+        "2.5.29.17" => certificate.subject_alt_names = subject_alt_names(value)?,
+        "2.5.29.19" => certificate.basic_constraints = Some(basic_constraints(value)?),
+        "2.5.29.35" => {
+            // keyIdentifier is [0] IMPLICIT, the issuer name and serial are rarely used
+            certificate.authority_key_id = children(expect(&elements(value)?, 0, 0x30)?)?
+                .iter()
+                .find(|item| item.tag == 0x80)
+                .map(|item| item.contents.to_vec());
+        }
+        "2.5.29.37" => {
+            certificate.extended_key_usage = children(expect(&elements(value)?, 0, 0x30)?)?
+                .iter()
+                .map(element_oid)
+                .collect::<Result<Vec<String>, String>>()?;
+        }
+        _ => {
+            let critical =
+                parts.len() == 3 && parts[1].contents.first().is_some_and(|&byte| byte != 0);
+            if critical {
+                certificate.unhandled_critical_extensions.push(id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse a DER certificate, v1 certificates without extensions included
+pub fn parse_der(bytes: &[u8]) -> Result<Certificate, String> {
+    let outer = elements(bytes)?;
+    let parts = children(expect(&outer, 0, 0x30)?)?;
+    let tbs = children(expect(&parts, 0, 0x30)?)?;
+    let signature_algorithm = children(expect(&parts, 1, 0x30)?)?;
+    let algorithm = element_oid(expect(&signature_algorithm, 0, 0x06)?)?;
+    // The first byte counts the unused bits
+    let signature = expect(&parts, 2, 0x03)?.contents.get(1..).unwrap_or(&[]);
+
+    // version is [0] EXPLICIT and left out for v1
+    let (version, index) = match tbs.first() {
+        Some(element) if element.tag == 0xa0 => {
+            // v1, v2 and v3 are stored as 0, 1 and 2
+            match expect(&elements(element.contents)?, 0, 0x02)?.contents {
+                &[version] if version <= 2 => (version + 1, 1),
+                _ => return Err("Invalid certificate version".into()),
+            }
+        }
+        _ => (1, 0),
+    };
+    let serial = expect(&tbs, index, 0x02)?.contents.to_vec();
+    let issuer = distinguished_name(expect(&tbs, index + 2, 0x30)?)?;
+    let validity = children(expect(&tbs, index + 3, 0x30)?)?;
+    if validity.len() != 2 {
+        return Err("Invalid validity".into());
+    }
+    let subject = distinguished_name(expect(&tbs, index + 4, 0x30)?)?;
+    let spki = expect(&tbs, index + 5, 0x30)?;
+
+    let mut certificate = Certificate::new(subject, parse_time(&validity[1])?, algorithm);
+    certificate.version = version;
+    certificate.serial = serial;
+    certificate.issuer = issuer;
+    certificate.not_before = parse_time(&validity[0])?;
+    certificate.public_key = public_key(spki)?;
+    certificate.subject_public_key = children(spki)?
+        .get(1)
+        .and_then(|key| key.contents.get(1..))
+        .unwrap_or(&[])
+        .to_vec();
+    certificate.tbs_certificate = parts[0].raw.to_vec();
+    certificate.signature = signature.to_vec();
+    if certificate.algorithm == "1.2.840.113549.1.1.10" {
+        certificate.pss = Some(match signature_algorithm.get(1) {
+            Some(parameters) => pss_parameters(parameters)?,
+            None => Default::default(),
+        });
+    }
+    // Keys ssh can't represent, like explicit curve parameters,
+    // stay Algorithm::Unknown, which never pairs
+    let (algorithm, public_blob) =
+        subject_public_key_info(spki.raw).unwrap_or((Algorithm::Unknown, vec![]));
+    certificate.key.is_public = true;
+    certificate.key.algorithm = algorithm;
+    certificate.key.public_blob = public_blob;
+    // After subjectPublicKeyInfo and the rarely seen unique IDs
+    if let Some(extensions) = tbs
+        .iter()
+        .skip(index + 6)
+        .find(|element| element.tag == 0xa3)
+    {
+        for extension in children(expect(&elements(extensions.contents)?, 0, 0x30)?)? {
+            add_extension(&mut certificate, &extension)?;
+        }
+    }
+    Ok(certificate)
+}
+
 pub fn parse(bytes: &[u8]) -> Result<Certificate, String> {
     let block: nom_pem::Block = nom_pem::decode_block(bytes).map_err(strerr)?;
-    let (_x, xcert) = x509_parser::x509_parser(&block.data).map_err(|nie| format!("{:?}", nie))?;
-    let tbs = xcert.tbs_certificate;
-    Ok(Certificate::new(
-        tbs.subject.to_string(),
-        tbs.validity.not_after,
-        xcert.signature_algorithm.algorithm.to_string(),
-    ))
+    parse_der(&block.data)
+}
+
+pub fn has_certificates(bytes: &[u8]) -> bool {
+    let begin: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    bytes.windows(begin.len()).any(|window| window == begin)
+}
+
+/// Parse every CERTIFICATE block of a PEM file, skipping any text
+/// or other blocks between them. Blocks that don't parse are kept
+/// as errors, so one bad certificate doesn't hide the rest.
+pub fn parse_bundle(bytes: &[u8]) -> Result<Bundle, String> {
+    let begin: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    let end: &[u8] = b"-----END CERTIFICATE-----";
+    let mut bundle: Bundle = Default::default();
+    let mut rest = bytes;
+    let mut block = 0;
+    while let Some(start) = rest.windows(begin.len()).position(|window| window == begin) {
+        block += 1;
+        rest = &rest[start..];
+        let length = match rest.windows(end.len()).position(|window| window == end) {
+            Some(position) => position + end.len(),
+            None => {
+                bundle
+                    .errors
+                    .push((block, "Missing -----END CERTIFICATE-----".into()));
+                break;
+            }
+        };
+        match parse(&rest[..length]) {
+            Ok(certificate) => bundle.certificates.push(certificate),
+            Err(error) => bundle.errors.push((block, error)),
+        }
+        rest = &rest[length..];
+    }
+    if bundle.certificates.is_empty() {
+        return match bundle.errors.first() {
+            Some(&(block, ref error)) => Err(format!("Certificate {}: {}", block, error)),
+            None => Err("No certificates".into()),
+        };
+    }
+    Ok(bundle)
+}
+
+/// Extensions certificates and bundles are kept under
+pub fn is_certificate_file(path: &Path) -> bool {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => ["pem", "crt", "cer", "ca-bundle"]
+            .iter()
+            .any(|known| extension.eq_ignore_ascii_case(known)),
+        None => false,
+    }
 }
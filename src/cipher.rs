@@ -0,0 +1,207 @@
+// Symmetric ciphers that protect encrypted private keys, by their OpenSSH names
+use aes::cipher::block_padding::NoPadding;
+use aes::cipher::consts::U16;
+use aes::cipher::{
+    BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyInit, KeyIvInit, StreamCipher,
+    StreamCipherSeek,
+};
+use aes::{Aes128, Aes192, Aes256};
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use chacha20::ChaCha20Legacy;
+use des::TdesEde3;
+use md5::{Digest, Md5};
+use poly1305::Poly1305;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Sizes in bytes a cipher needs from the key derivation,
+/// and how it shapes the encrypted data
+#[derive(PartialEq, Eq, Debug)]
+pub struct Spec {
+    pub key_length: usize,
+    pub iv_length: usize,
+    pub block_size: usize,
+    // Authentication tag appended by the AEAD ciphers
+    pub tag_length: usize,
+}
+
+pub fn spec(name: &str) -> Option<Spec> {
+    let (key_length, iv_length, block_size, tag_length) = match name {
+        "3des-cbc" => (24, 8, 8, 0),
+        "aes128-cbc" | "aes128-ctr" => (16, 16, 16, 0),
+        "aes192-cbc" | "aes192-ctr" => (24, 16, 16, 0),
+        "aes256-cbc" | "aes256-ctr" => (32, 16, 16, 0),
+        "aes128-gcm@openssh.com" => (16, 12, 16, 16),
+        "aes256-gcm@openssh.com" => (32, 12, 16, 16),
+        // Two 256 bit keys, the IV is the (zero) packet sequence number
+        "chacha20-poly1305@openssh.com" => (64, 0, 8, 16),
+        _ => return None,
+    };
+    Some(Spec {
+        key_length,
+        iv_length,
+        block_size,
+        tag_length,
+    })
+}
+
+fn cbc<C>(key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), String>
+where
+    C: BlockCipher + BlockDecryptMut + KeyInit,
+{
+    cbc::Decryptor::<C>::new_from_slices(key, iv)
+        .map_err(|_| "Invalid key or IV length".to_string())?
+        .decrypt_padded_mut::<NoPadding>(data)
+        .map_err(|_| "Encrypted data is not a whole number of blocks".to_string())?;
+    Ok(())
+}
+
+fn ctr<C>(key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), String>
+where
+    C: BlockCipher<BlockSize = U16> + BlockEncryptMut + KeyInit,
+{
+    ctr::Ctr128BE::<C>::new_from_slices(key, iv)
+        .map_err(|_| "Invalid key or IV length".to_string())?
+        .apply_keystream(data);
+    Ok(())
+}
+
+fn gcm<C>(key: &[u8], iv: &[u8], data: &mut [u8], tag: &[u8]) -> Result<(), String>
+where
+    C: AeadInPlace + KeyInit,
+{
+    if iv.len() != 12 || tag.len() != 16 {
+        return Err("Invalid IV or tag length".into());
+    }
+    C::new_from_slice(key)
+        .map_err(|_| "Invalid key length".to_string())?
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(iv),
+            b"",
+            data,
+            GenericArray::from_slice(tag),
+        )
+        .map_err(|_| "Incorrect passphrase".into())
+}
+
+// chacha20-poly1305@openssh.com as used for key files: no additional data,
+// sequence number 0, see PROTOCOL.chacha20poly1305 in OpenSSH
+fn chacha20_poly1305(key: &[u8], data: &mut [u8], tag: &[u8]) -> Result<(), String> {
+    if key.len() != 64 {
+        return Err("Invalid key length".into());
+    }
+    let mut cipher = ChaCha20Legacy::new_from_slices(&key[..32], &[0u8; 8])
+        .map_err(|_| "Invalid key length".to_string())?;
+    // The first block of keystream is the Poly1305 key
+    let mut poly_key = [0u8; 32];
+    cipher.apply_keystream(&mut poly_key);
+    let expected = Poly1305::new_from_slice(&poly_key)
+        .map_err(|_| "Invalid key length".to_string())?
+        .compute_unpadded(data);
+    poly_key.zeroize();
+    if expected.as_slice() != tag {
+        return Err("Incorrect passphrase".into());
+    }
+    cipher.seek(64u64);
+    cipher.apply_keystream(data);
+    Ok(())
+}
+
+/// OpenSSL's EVP_BytesToKey with MD5 and a single iteration,
+/// which legacy PEM encryption uses to turn the passphrase into a key
+pub fn evp_bytes_to_key(passphrase: &[u8], salt: &[u8], key_length: usize) -> Zeroizing<Vec<u8>> {
+    let mut key = Zeroizing::new(Vec::with_capacity(key_length + 16));
+    let mut previous = Zeroizing::new(vec![]);
+    while key.len() < key_length {
+        let mut md5 = Md5::new();
+        md5.update(&*previous);
+        md5.update(passphrase);
+        md5.update(salt);
+        previous = Zeroizing::new(md5.finalize().to_vec());
+        key.extend_from_slice(&previous);
+    }
+    key.truncate(key_length);
+    key
+}
+
+/// Strip PKCS#7 padding. A wrong key almost always garbles it.
+pub fn unpad(data: &mut Vec<u8>, block_size: usize) -> Result<(), String> {
+    let padding = data.last().cloned().unwrap_or(0) as usize;
+    if padding == 0
+        || padding > block_size
+        || padding > data.len()
+        || data[data.len() - padding..]
+            .iter()
+            .any(|&byte| byte as usize != padding)
+    {
+        return Err("Incorrect passphrase".into());
+    }
+    let length = data.len() - padding;
+    data.truncate(length);
+    Ok(())
+}
+
+/// Decrypt `data` in place. `tag` is only used by the AEAD ciphers.
+pub fn decrypt(
+    name: &str,
+    key: &[u8],
+    iv: &[u8],
+    data: &mut [u8],
+    tag: &[u8],
+) -> Result<(), String> {
+    match name {
+        "3des-cbc" => cbc::<TdesEde3>(key, iv, data),
+        "aes128-cbc" => cbc::<Aes128>(key, iv, data),
+        "aes192-cbc" => cbc::<Aes192>(key, iv, data),
+        "aes256-cbc" => cbc::<Aes256>(key, iv, data),
+        "aes128-ctr" => ctr::<Aes128>(key, iv, data),
+        "aes192-ctr" => ctr::<Aes192>(key, iv, data),
+        "aes256-ctr" => ctr::<Aes256>(key, iv, data),
+        "aes128-gcm@openssh.com" => gcm::<Aes128Gcm>(key, iv, data, tag),
+        "aes256-gcm@openssh.com" => gcm::<Aes256Gcm>(key, iv, data, tag),
+        "chacha20-poly1305@openssh.com" => chacha20_poly1305(key, data, tag),
+        _ => Err(format!("Unsupported cipher {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, evp_bytes_to_key, spec, unpad};
+
+    #[test]
+    fn test_spec() {
+        assert_eq!(spec("aes256-ctr").unwrap().key_length, 32);
+        assert_eq!(spec("3des-cbc").unwrap().block_size, 8);
+        assert_eq!(spec("aes128-gcm@openssh.com").unwrap().tag_length, 16);
+        assert_eq!(spec("none"), None);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_partial_blocks() {
+        let mut data = [0u8; 15];
+        assert!(decrypt("aes128-cbc", &[0u8; 16], &[0u8; 16], &mut data, &[]).is_err());
+        assert!(decrypt("rc4", &[], &[], &mut data, &[]).is_err());
+    }
+
+    #[test]
+    fn test_unpad() {
+        let mut data = vec![9, 9, 3, 3, 3];
+        assert!(unpad(&mut data, 8).is_ok());
+        assert_eq!(data, vec![9, 9]);
+        assert!(unpad(&mut vec![9, 9, 2, 3], 8).is_err());
+        assert!(unpad(&mut vec![9, 9, 0], 8).is_err());
+        assert!(unpad(&mut vec![], 8).is_err());
+    }
+
+    #[test]
+    fn test_evp_bytes_to_key() {
+        // openssl enc -aes-256-cbc -k tealeaves -S 0102030405060708 -P -md md5
+        let key = evp_bytes_to_key(b"tealeaves", &[1, 2, 3, 4, 5, 6, 7, 8], 32);
+        let hex: Vec<String> = key.iter().map(|byte| format!("{:02X}", byte)).collect();
+        assert_eq!(
+            hex.concat(),
+            "8D647E0E3D2AF10CFE6EFC54181D7A31F854B2BBFE69B39B5693EDF12FB71B4F"
+        );
+    }
+}
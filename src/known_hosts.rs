@@ -0,0 +1,523 @@
+use base64;
+use hmac::{Hmac, Mac};
+use ring::rand::{SecureRandom, SystemRandom};
+use rsfs::{File, GenFS, Metadata, Permissions};
+use sha1::Sha1;
+use ssh_key::{peek_algorithm, Algorithm, SshKey};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq)]
+pub enum Marker {
+    CertAuthority,
+    Revoked,
+}
+
+impl Marker {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Marker::CertAuthority => "@cert-authority",
+            Marker::Revoked => "@revoked",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Hosts {
+    // Comma-separated patterns like `example.com`, `[example.com]:2222`,
+    // `*.example.com` or `!bad.example.com`
+    Patterns(Vec<String>),
+    // `|1|salt|hash`, HMAC-SHA1 of the host name keyed with the salt
+    Hashed(Vec<u8>, Vec<u8>),
+}
+
+/// One host key line of a known_hosts file
+#[derive(Debug)]
+pub struct Entry {
+    // 1-based line number
+    pub line: usize,
+    pub marker: Option<Marker>,
+    pub hosts: Hosts,
+    // Key type as written, like ssh-ed25519
+    pub key_type: String,
+    pub key: SshKey,
+}
+
+/// `*` and `?` wildcards, case-insensitive like OpenSSH's match_pattern
+pub fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p.eq_ignore_ascii_case(n) => {
+            wildcard_match(&pattern[1..], &name[1..])
+        }
+        _ => false,
+    }
+}
+
+/// How a host appears in known_hosts: bare on port 22, `[host]:port` otherwise
+pub fn host_name(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_owned()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+impl Entry {
+    /// Whether this entry applies to `host` on `port`
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        let name = host_name(host, port);
+        match self.hosts {
+            Hosts::Hashed(ref salt, ref hash) => {
+                let mut mac =
+                    Hmac::<Sha1>::new_from_slice(salt).expect("HMAC takes any key length");
+                mac.update(name.as_bytes());
+                mac.verify_slice(hash).is_ok()
+            }
+            Hosts::Patterns(ref patterns) => {
+                let mut matched = false;
+                for pattern in patterns {
+                    if pattern.starts_with('!') {
+                        if wildcard_match(&pattern.as_bytes()[1..], name.as_bytes()) {
+                            return false;
+                        }
+                    } else if wildcard_match(pattern.as_bytes(), name.as_bytes()) {
+                        matched = true;
+                    }
+                }
+                matched
+            }
+        }
+    }
+
+    // Lists `name` literally, unlike `matches` which also follows wildcards
+    fn hosts_contains(&self, name: &str) -> bool {
+        match self.hosts {
+            Hosts::Patterns(ref patterns) => patterns.iter().any(|pattern| pattern == name),
+            Hosts::Hashed(..) => false,
+        }
+    }
+
+    /// The host patterns, or "hashed host" when they can't be known
+    pub fn hosts_description(&self) -> String {
+        match self.hosts {
+            Hosts::Patterns(ref patterns) => patterns.join(","),
+            Hosts::Hashed(..) => "hashed host".into(),
+        }
+    }
+
+    /// ssh-dss and RSA keys under 2048 bits
+    pub fn is_weak(&self) -> bool {
+        match self.key.algorithm {
+            Algorithm::Dsa(_) => true,
+            Algorithm::Rsa(ref modulus) => modulus.len() < 2048 / 8,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct KnownHosts {
+    pub entries: Vec<Entry>,
+    // Line number and message for lines that aren't valid entries
+    pub errors: Vec<(usize, String)>,
+}
+
+impl KnownHosts {
+    /// Every entry for `host` on `port`, including markers
+    pub fn lookup(&self, host: &str, port: u16) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.matches(host, port))
+            .collect()
+    }
+
+    /// Number of entries per key type, in order of first appearance
+    pub fn key_type_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = vec![];
+        for entry in &self.entries {
+            match counts
+                .iter_mut()
+                .find(|(key_type, _)| *key_type == entry.key_type)
+            {
+                Some(count) => count.1 += 1,
+                None => counts.push((entry.key_type.clone(), 1)),
+            }
+        }
+        counts
+    }
+
+    /// Plain host names with more than one key of the same type,
+    /// and the lines of those keys
+    pub fn conflicts(&self) -> Vec<(String, String, Vec<usize>)> {
+        let mut conflicts: Vec<(String, String, Vec<usize>)> = vec![];
+        for (index, entry) in self.entries.iter().enumerate() {
+            if entry.marker.is_some() {
+                continue;
+            }
+            let patterns = match entry.hosts {
+                Hosts::Patterns(ref patterns) => patterns,
+                Hosts::Hashed(..) => continue,
+            };
+            for pattern in patterns {
+                if pattern.contains(&['*', '?', '!'][..]) {
+                    continue;
+                }
+                if conflicts
+                    .iter()
+                    .any(|(host, key_type, _)| host == pattern && *key_type == entry.key_type)
+                {
+                    continue;
+                }
+                let mut lines = vec![entry.line];
+                for other in &self.entries[index + 1..] {
+                    if other.marker.is_none()
+                        && other.key_type == entry.key_type
+                        && other.key.public_blob != entry.key.public_blob
+                        && other.hosts_contains(pattern)
+                    {
+                        lines.push(other.line);
+                    }
+                }
+                if lines.len() > 1 {
+                    conflicts.push((pattern.clone(), entry.key_type.clone(), lines));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+fn parse_hosts(field: &str) -> Result<Hosts, String> {
+    if let Some(hashed) = field.strip_prefix("|1|") {
+        let mut parts = hashed.splitn(2, '|');
+        let salt = parts.next().unwrap_or("");
+        let hash = parts
+            .next()
+            .ok_or_else(|| "Invalid hashed host".to_string())?;
+        let salt = base64::decode(salt).map_err(|_| "Invalid Base64".to_string())?;
+        let hash = base64::decode(hash).map_err(|_| "Invalid Base64".to_string())?;
+        return Ok(Hosts::Hashed(salt, hash));
+    }
+    Ok(Hosts::Patterns(
+        field.split(',').map(String::from).collect(),
+    ))
+}
+
+fn parse_line(line: &str) -> Result<(Option<Marker>, Hosts, String, SshKey), String> {
+    let mut fields = line.split_whitespace().peekable();
+    let marker = match fields.peek() {
+        Some(&"@cert-authority") => Some(Marker::CertAuthority),
+        Some(&"@revoked") => Some(Marker::Revoked),
+        Some(field) if field.starts_with('@') => return Err(format!("Unknown marker {}", field)),
+        _ => None,
+    };
+    if marker.is_some() {
+        fields.next();
+    }
+    let hosts = parse_hosts(fields.next().ok_or_else(|| "Missing hosts".to_string())?)?;
+    let key_type = fields
+        .next()
+        .ok_or_else(|| "Missing key type".to_string())?;
+    let payload = fields.next().ok_or_else(|| "Missing key".to_string())?;
+    let key_bytes = base64::decode(payload).map_err(|_| "Invalid Base64".to_string())?;
+    let comment: Vec<&str> = fields.collect();
+    let ssh_key = SshKey {
+        algorithm: peek_algorithm(false, &key_bytes)?,
+        comment: if comment.is_empty() {
+            None
+        } else {
+            Some(comment.join(" "))
+        },
+        is_public: true,
+        public_blob: key_bytes,
+        ..Default::default()
+    };
+    Ok((marker, hosts, key_type.to_owned(), ssh_key))
+}
+
+/// Parse every entry of a known_hosts file, skipping blank lines and comments
+pub fn parse(bytes: &[u8]) -> KnownHosts {
+    let mut known_hosts: KnownHosts = Default::default();
+    for (index, line) in String::from_utf8_lossy(bytes).lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line) {
+            Ok((marker, hosts, key_type, key)) => known_hosts.entries.push(Entry {
+                line: index + 1,
+                marker,
+                hosts,
+                key_type,
+                key,
+            }),
+            Err(error) => known_hosts.errors.push((index + 1, error)),
+        }
+    }
+    known_hosts
+}
+
+/// Files named like known_hosts, known_hosts2 or known_hosts.old
+pub fn is_known_hosts(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with("known_hosts"))
+        .unwrap_or(false)
+}
+
+/// What `maintain` does to a known_hosts file
+#[derive(Debug)]
+pub struct Maintenance {
+    // Replace plain host names with `|1|salt|hash`, like ssh-keygen -H
+    pub hash: bool,
+    // Merge the hosts of entries with the same key into one entry
+    pub dedupe: bool,
+    // Drop entries with these key types. @revoked entries are always kept.
+    pub banned_key_types: Vec<String>,
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self {
+            hash: true,
+            dedupe: true,
+            banned_key_types: vec!["ssh-dss".into()],
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MaintenanceReport {
+    // Host names hashed
+    pub hashed: usize,
+    // Entries merged into an earlier entry with the same key
+    pub merged: usize,
+    // Entries with a banned key type
+    pub dropped: usize,
+}
+
+impl MaintenanceReport {
+    pub fn is_unchanged(&self) -> bool {
+        *self == Default::default()
+    }
+}
+
+// A line on its way through `rewrite`. Comments, blank lines and lines
+// that don't parse have no entry and are kept as they are.
+struct RewriteLine<'a> {
+    original: &'a str,
+    entry: Option<(Option<Marker>, Hosts, String, SshKey)>,
+    changed: bool,
+}
+
+fn hash_host(random: &SystemRandom, name: &str) -> Result<String, String> {
+    let mut salt = [0u8; 20];
+    random
+        .fill(&mut salt)
+        .map_err(|_| "Error generating a salt".to_string())?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&salt).expect("HMAC takes any key length");
+    mac.update(name.as_bytes());
+    Ok(format!(
+        "|1|{}|{}",
+        base64::encode(&salt),
+        base64::encode(&mac.finalize().into_bytes())
+    ))
+}
+
+fn format_entry(marker: &Option<Marker>, hosts: &str, key_type: &str, key: &SshKey) -> String {
+    let mut line = String::new();
+    if let Some(ref marker) = *marker {
+        line.push_str(marker.as_str());
+        line.push(' ');
+    }
+    line.push_str(&format!(
+        "{} {} {}",
+        hosts,
+        key_type,
+        base64::encode(&key.public_blob)
+    ));
+    if let Some(ref comment) = key.comment {
+        line.push(' ');
+        line.push_str(comment);
+    }
+    line
+}
+
+/// The rewritten known_hosts contents and what changed
+pub fn rewrite(
+    bytes: &[u8],
+    maintenance: &Maintenance,
+) -> Result<(String, MaintenanceReport), String> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut report: MaintenanceReport = Default::default();
+    let mut lines: Vec<RewriteLine> = vec![];
+    for original in text.lines() {
+        let trimmed = original.trim();
+        let entry = if trimmed.is_empty() || trimmed.starts_with('#') {
+            None
+        } else {
+            parse_line(trimmed).ok()
+        };
+        let (marker, hosts, key_type, key) = match entry {
+            Some(entry) => entry,
+            None => {
+                lines.push(RewriteLine {
+                    original,
+                    entry: None,
+                    changed: false,
+                });
+                continue;
+            }
+        };
+        if marker != Some(Marker::Revoked) && maintenance.banned_key_types.contains(&key_type) {
+            report.dropped += 1;
+            continue;
+        }
+        if maintenance.dedupe {
+            let earlier = lines.iter_mut().find(|line| match line.entry {
+                Some((ref earlier_marker, ref earlier_hosts, _, ref earlier_key)) => {
+                    *earlier_marker == marker
+                        && earlier_key.public_blob == key.public_blob
+                        && match (earlier_hosts, &hosts) {
+                            (&Hosts::Patterns(_), &Hosts::Patterns(_)) => true,
+                            (hashed, other) => hashed == other,
+                        }
+                }
+                None => false,
+            });
+            if let Some(earlier) = earlier {
+                if let (
+                    Some((_, Hosts::Patterns(ref mut earlier_patterns), _, _)),
+                    Hosts::Patterns(patterns),
+                ) = (earlier.entry.as_mut(), hosts)
+                {
+                    for pattern in patterns {
+                        if !earlier_patterns.contains(&pattern) {
+                            earlier_patterns.push(pattern);
+                            earlier.changed = true;
+                        }
+                    }
+                }
+                report.merged += 1;
+                continue;
+            }
+        }
+        lines.push(RewriteLine {
+            original,
+            entry: Some((marker, hosts, key_type, key)),
+            changed: false,
+        });
+    }
+
+    let random = SystemRandom::new();
+    let mut output = String::new();
+    for line in &lines {
+        match line.entry {
+            Some((None, Hosts::Patterns(ref patterns), ref key_type, ref key))
+                if maintenance.hash
+                    && !patterns
+                        .iter()
+                        .any(|pattern| pattern.contains(&['*', '?', '!'][..])) =>
+            {
+                // One hashed line per host name, as ssh-keygen -H does
+                for pattern in patterns {
+                    let hosts = hash_host(&random, pattern)?;
+                    output.push_str(&format_entry(&None, &hosts, key_type, key));
+                    output.push('\n');
+                    report.hashed += 1;
+                }
+                continue;
+            }
+            Some((ref marker, Hosts::Patterns(ref patterns), ref key_type, ref key))
+                if line.changed =>
+            {
+                output.push_str(&format_entry(marker, &patterns.join(","), key_type, key));
+            }
+            _ => output.push_str(line.original),
+        }
+        output.push('\n');
+    }
+    Ok((output, report))
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Rewrite a known_hosts file as `maintenance` says. The new contents go to a
+/// temporary file that is renamed over the original, which is kept as `.old`.
+/// Files that need no changes are left alone.
+pub fn maintain<
+    P: Permissions,
+    M: Metadata<Permissions = P>,
+    F: GenFS<Permissions = P, Metadata = M>,
+>(
+    fs: &F,
+    path: &Path,
+    maintenance: &Maintenance,
+) -> Result<MaintenanceReport, String> {
+    let error =
+        |action: &str, error: io::Error| format!("Error {} {}: {}", action, path.display(), error);
+    let mut bytes = vec![];
+    fs.open_file(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|err| error("reading", err))?;
+    let permissions = fs
+        .metadata(path)
+        .map_err(|err| error("reading", err))?
+        .permissions();
+    let (contents, report) = rewrite(&bytes, maintenance)?;
+    if report.is_unchanged() {
+        return Ok(report);
+    }
+    let temporary = sibling(path, ".tmp");
+    let backup = sibling(path, ".old");
+    fs.create_file(&temporary)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs.set_permissions(&temporary, permissions))
+        .map_err(|err| error("writing the new", err))?;
+    match fs.remove_file(&backup) {
+        Err(ref err) if err.kind() != io::ErrorKind::NotFound => {
+            return Err(format!("Error removing {}: {}", backup.display(), err))
+        }
+        _ => (),
+    }
+    fs.hard_link(path, &backup)
+        .map_err(|err| error("backing up", err))?;
+    fs.rename(&temporary, path)
+        .map_err(|err| error("replacing", err))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_known_hosts, wildcard_match};
+    use std::path::Path;
+
+    #[test]
+    fn test_is_known_hosts() {
+        assert!(is_known_hosts(Path::new("/home/a/.ssh/known_hosts")));
+        assert!(is_known_hosts(Path::new("known_hosts.old")));
+        assert!(!is_known_hosts(Path::new("/home/a/.ssh/id_rsa")));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match(b"*.example.com", b"www.Example.com"));
+        assert!(wildcard_match(b"host?", b"host1"));
+        assert!(wildcard_match(b"[*]:2222", b"[git.example.com]:2222"));
+        assert!(!wildcard_match(b"*.example.com", b"example.com"));
+        assert!(!wildcard_match(b"host?", b"host"));
+    }
+}
@@ -1,6 +1,344 @@
 use std::{fmt, path};
-use ssh_key::{Algorithm, SshKey};
-use certificate::Certificate;
+use ssh_key::{Algorithm, Kdf, SecurityKey, SshKey, MIN_PBKDF2_ITERATIONS};
+use authorized_keys::{self, AuthorizedKeys};
+use certificate::{format_date, Bundle, Certificate, Chain};
+use known_hosts::{KnownHosts, Marker};
+use ssh_certificate::SshCertificate;
+use ssh_config::SshConfig;
+use time;
+use trust_store::TrustStore;
+
+// Flags and key handle only exist in the private key stub
+fn security_key_details(security_key: &SecurityKey) -> String {
+    let mut output = String::new();
+    match security_key.user_presence_required() {
+        Some(true) => output.push_str("\n\t✓ requires touching the security key"),
+        Some(false) => output.push_str("\n\t⚠️ does not require touching the security key"),
+        None => (),
+    }
+    if security_key.user_verification_required() == Some(true) {
+        output.push_str("\n\t✓ requires PIN or biometric verification");
+    }
+    if security_key.is_resident() == Some(true) {
+        output.push_str("\n\tresident key, stored on the security key");
+    }
+    if let Some(length) = security_key.key_handle_length {
+        output.push_str(&format!("\n\tkey handle is {} bytes", length));
+    }
+    output
+}
+
+fn ssh_key_details(key: &SshKey) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("\n\t✓ {}", key));
+    if let Some(fingerprint) = key.fingerprint_sha256() {
+        output.push_str(&format!("\n\t{}", fingerprint));
+    }
+    if let Some(fingerprint) = key.fingerprint_md5() {
+        output.push_str(&format!("\n\t{}", fingerprint));
+    }
+    for error in &key.errors {
+        output.push_str(&format!("\n\t🚨 {}", error));
+    }
+    if let Some(ref cipher) = key.cipher {
+        output.push_str(&format!("\n\tencrypted with {}", cipher));
+        if let Some(ref iv) = key.cipher_iv {
+            let hex: Vec<String> = iv.iter().map(|byte| format!("{:02X}", byte)).collect();
+            output.push_str(&format!(", IV {}", hex.concat()));
+        }
+    }
+    if let Some(ref kdf) = key.kdf {
+        output.push_str(&format!("\n\tpassphrase protected by {}", kdf));
+    }
+    if let Some(ref format) = key.legacy_format {
+        if key.is_encrypted {
+            output.push_str(&format!(
+                "\n\t🔥 legacy {} key format, remove the passphrase with the {} tools \
+                 and convert with ssh-keygen -i",
+                format, format
+            ));
+        } else {
+            output.push_str(&format!(
+                "\n\t🔥 legacy {} key format, convert with ssh-keygen -i",
+                format
+            ));
+        }
+    }
+    if key.has_legacy_cipher() {
+        match key.kdf {
+            Some(Kdf::Bcrypt(..)) => {
+                output.push_str("\n\t⚠️ legacy cbc cipher, re-encrypt with ssh-keygen -p")
+            }
+            _ => output.push_str(
+                "\n\t⚠️ legacy des cipher, re-encrypt with openssl pkcs8 -topk8 -v2 aes-256-cbc",
+            ),
+        }
+    }
+    match key.kdf {
+        // EVP_BytesToKey has no rounds to raise, and ssh-keygen -a ignores PEM
+        Some(Kdf::OpensslMd5) => output.push_str(
+            "\n\t⚠️ single-pass md5 key derivation, convert with ssh-keygen -p -o \
+             or openssl pkcs8 -topk8 -v2 aes-256-cbc",
+        ),
+        Some(Kdf::PuttySha1) => {
+            output.push_str("\n\t⚠️ unsalted sha1 key derivation, re-save as PPK v3 in puttygen")
+        }
+        // The legacy format warning above already says how to convert away from it
+        Some(Kdf::SshcomMd5) => output.push_str("\n\t⚠️ unsalted md5 key derivation"),
+        Some(Kdf::Pbes1(_)) => output.push_str(
+            "\n\t⚠️ legacy pbes1 encryption, re-encrypt with openssl pkcs8 -topk8 -v2 aes-256-cbc",
+        ),
+        Some(Kdf::Pbkdf2(..)) if key.has_weak_pbe() => output.push_str(&format!(
+            "\n\t⚠️ fewer than {} pbkdf2 iterations, \
+             re-encrypt with openssl pkcs8 -topk8 -v2 aes-256-cbc -iter {}",
+            MIN_PBKDF2_ITERATIONS, MIN_PBKDF2_ITERATIONS
+        )),
+        _ => (),
+    }
+
+    match key.algorithm {
+        Algorithm::Rsa(ref modulus)
+            if (!key.is_encrypted || key.is_decrypted) && modulus.len() < (2048 / 8) =>
+        {
+            output.push_str("\n\t⚠️ RSA keys should be 2048 bits or larger");
+        }
+        Algorithm::Dsa(_) => {
+            output.push_str("\n\t⚠️ dsa keys are considered insecure");
+        }
+        Algorithm::Ecdsa(_, _) => {
+            output.push_str("\n\t⚠️ ecdsa keys are considered insecure");
+        }
+        Algorithm::SkEd25519(_, ref security_key) | Algorithm::SkEcdsa(_, _, ref security_key) => {
+            output.push_str(&security_key_details(security_key));
+        }
+        _ => (),
+    }
+    // TODO figure out how to handle this
+    // if !key.is_public && self.mode.unwrap_or(0o000) & 0o077 != 0o000 {
+    //     output.push_str("\n\t⚠️ insecure permissions");
+    // }
+    output
+}
+
+fn authorized_keys_details(path: &path::Path, authorized_keys: &AuthorizedKeys) -> String {
+    let mut output = String::new();
+    output.push_str(&format!(
+        "\n\t✓ authorized_keys with {} keys",
+        authorized_keys.entries.len()
+    ));
+    let service_account = authorized_keys::service_account(path);
+    let duplicates = authorized_keys.duplicates();
+    for entry in &authorized_keys.entries {
+        output.push_str(&format!("\n\tline {}: {}", entry.line, entry.key));
+        if let Some(fingerprint) = entry.key.fingerprint_sha256() {
+            output.push_str(&format!(" {}", fingerprint));
+        }
+        if let Some(ref comment) = entry.key.comment {
+            output.push_str(&format!(" {}", comment));
+        }
+        if !entry.options.is_empty() {
+            let options: Vec<String> = entry
+                .options
+                .iter()
+                .map(|(name, value)| match *value {
+                    Some(ref value) => format!("{}=\"{}\"", name, value.replace('"', "\\\"")),
+                    None => name.clone(),
+                })
+                .collect();
+            output.push_str(&format!("\n\t\toptions: {}", options.join(",")));
+        }
+        if authorized_keys::is_weak(&entry.key) {
+            match entry.key.algorithm {
+                Algorithm::Dsa(_) => output.push_str("\n\t\t⚠️ dsa keys are considered insecure"),
+                _ => output.push_str("\n\t\t⚠️ RSA keys should be 2048 bits or larger"),
+            }
+        }
+        for &(first, duplicate) in &duplicates {
+            if duplicate == entry.line {
+                output.push_str(&format!("\n\t\t⚠️ duplicate of the key on line {}", first));
+            }
+        }
+        if let Some(ref account) = service_account {
+            if entry.is_unrestricted() {
+                output.push_str(&format!(
+                    "\n\t\t⚠️ unrestricted key for service account {}, \
+                     limit it with restrict, command= or from=",
+                    account
+                ));
+            }
+        }
+    }
+    for &(line, ref error) in &authorized_keys.errors {
+        output.push_str(&format!("\n\tline {}: 🚨 {}", line, error));
+    }
+    output
+}
+
+fn known_hosts_details(known_hosts: &KnownHosts) -> String {
+    let mut output = String::new();
+    output.push_str(&format!(
+        "\n\t✓ known_hosts with {} entries",
+        known_hosts.entries.len()
+    ));
+    let counts: Vec<String> = known_hosts
+        .key_type_counts()
+        .iter()
+        .map(|(key_type, count)| format!("{} {}", count, key_type))
+        .collect();
+    if !counts.is_empty() {
+        output.push_str(&format!("\n\t{}", counts.join(", ")));
+    }
+    for entry in &known_hosts.entries {
+        let hosts = entry.hosts_description();
+        match entry.marker {
+            Some(Marker::CertAuthority) => output.push_str(&format!(
+                "\n\tline {}: @cert-authority {} for {}",
+                entry.line, entry.key_type, hosts
+            )),
+            Some(Marker::Revoked) => output.push_str(&format!(
+                "\n\tline {}: @revoked {} key for {}",
+                entry.line, entry.key_type, hosts
+            )),
+            None => (),
+        }
+        if entry.is_weak() {
+            match entry.key.algorithm {
+                Algorithm::Dsa(_) => output.push_str(&format!(
+                    "\n\tline {}: ⚠️ weak ssh-dss host key for {}",
+                    entry.line, hosts
+                )),
+                _ => output.push_str(&format!(
+                    "\n\tline {}: ⚠️ RSA host key under 2048 bits for {}",
+                    entry.line, hosts
+                )),
+            }
+        }
+    }
+    for (host, key_type, lines) in known_hosts.conflicts() {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        output.push_str(&format!(
+            "\n\t⚠️ conflicting {} keys for {} on lines {}",
+            key_type,
+            host,
+            lines.join(", ")
+        ));
+    }
+    for &(line, ref error) in &known_hosts.errors {
+        output.push_str(&format!("\n\tline {}: 🚨 {}", line, error));
+    }
+    output
+}
+
+// One line per certificate, numbered from 1 in file order, then the chain problems
+fn certificates_details(bundle: &Bundle) -> String {
+    let mut output = String::new();
+    output.push_str(&format!(
+        "\n\t✓ x509 certificate bundle with {} certificates",
+        bundle.certificates.len()
+    ));
+    for (index, certificate) in bundle.certificates.iter().enumerate() {
+        let kind = match (certificate.is_ca(), certificate.is_self_issued()) {
+            (true, true) => " (root CA)",
+            (true, false) => " (intermediate CA)",
+            (false, true) => " (self-signed)",
+            (false, false) => "",
+        };
+        let expiry = if certificate.is_expired() {
+            "🚨 expired"
+        } else {
+            "expires"
+        };
+        output.push_str(&format!(
+            "\n\t#{} {}{}, {} {}",
+            index + 1,
+            certificate.name(),
+            kind,
+            expiry,
+            format_date(&certificate.expires)
+        ));
+        // Clients don't check the signature on a root they trust
+        match certificate.weak_signature_hash() {
+            Some(hash) if !certificate.is_self_issued() => {
+                output.push_str(&format!(", 🚨 insecure {} signature", hash.to_uppercase()))
+            }
+            _ => (),
+        }
+    }
+    let numbers = |indexes: &[usize]| -> String {
+        let numbers: Vec<String> = indexes
+            .iter()
+            .map(|index| format!("#{}", index + 1))
+            .collect();
+        numbers.join(" → ")
+    };
+    for chain in bundle.chains() {
+        let last = &bundle.certificates[chain.indexes[chain.indexes.len() - 1]];
+        let chain_numbers = numbers(&chain.indexes);
+        match chain.missing_issuer {
+            // An intermediate's issuer is normally a root the client already trusts
+            Some(ref issuer) if last.is_ca() => output.push_str(&format!(
+                "\n\tchain {}, ends at root {} which isn't in the bundle",
+                chain_numbers, issuer
+            )),
+            Some(ref issuer) => output.push_str(&format!(
+                "\n\t🚨 chain {} is missing the intermediate {}",
+                chain_numbers, issuer
+            )),
+            None if chain.indexes.len() > 1 => {
+                output.push_str(&format!("\n\tchain {}", chain_numbers))
+            }
+            None => (),
+        }
+        if !chain.is_in_order() {
+            output.push_str(&format!(
+                "\n\t⚠️ chain {} is out of order, each certificate should come before its issuer",
+                chain_numbers
+            ));
+        }
+    }
+    for (first, duplicate) in bundle.duplicates() {
+        output.push_str(&format!(
+            "\n\t⚠️ #{} is a duplicate of #{}",
+            duplicate + 1,
+            first + 1
+        ));
+    }
+    for &(block, ref error) in &bundle.errors {
+        output.push_str(&format!(
+            "\n\t🚨 CERTIFICATE block {} skipped: {}",
+            block, error
+        ));
+    }
+    output
+}
+
+// Files other than `path` itself are named, so findings in Includes can be found
+fn ssh_config_details(path: &path::Path, ssh_config: &SshConfig) -> String {
+    let mut output = String::new();
+    output.push_str(&format!(
+        "\n\t✓ ssh_config with {} options",
+        ssh_config.directives.len()
+    ));
+    let location = |file: &path::Path, line: usize| {
+        if file == path {
+            format!("line {}", line)
+        } else {
+            format!("{} line {}", file.display(), line)
+        }
+    };
+    for finding in ssh_config.audit() {
+        output.push_str(&format!(
+            "\n\t{}: {}: {}",
+            location(&finding.file, finding.line),
+            finding.severity,
+            finding.message
+        ));
+    }
+    for (file, line, error) in &ssh_config.errors {
+        output.push_str(&format!("\n\t{}: 🚨 {}", location(file, *line), error));
+    }
+    output
+}
 
 #[derive(Debug)]
 pub enum Leaf {
@@ -13,7 +351,74 @@ pub enum Leaf {
     MediumFile(path::PathBuf),
     LargeFile(path::PathBuf),
     SshKey(path::PathBuf, SshKey),
+    // openssh-key-v1 files can hold more than one key
+    SshKeys(path::PathBuf, Vec<SshKey>),
     Certificate(path::PathBuf, Certificate),
+    // PEM files with more than one certificate, like fullchain.pem
+    Certificates(path::PathBuf, Bundle),
+    SshCertificate(path::PathBuf, SshCertificate),
+    AuthorizedKeys(path::PathBuf, AuthorizedKeys),
+    KnownHosts(path::PathBuf, KnownHosts),
+    SshConfig(path::PathBuf, SshConfig),
+}
+
+impl Leaf {
+    /// The trusted certificate each chain of a certificate leaf verifies to,
+    /// or the first reason it doesn't. Lone trusted roots, like the
+    /// certificates of a CA bundle, pass without comment.
+    pub fn trust_findings(&self, store: &TrustStore, now: &time::Tm) -> Vec<String> {
+        let (certificates, chains): (Vec<&Certificate>, Vec<Chain>) = match *self {
+            Leaf::Certificate(_, ref certificate) => (
+                vec![certificate],
+                vec![Chain {
+                    indexes: vec![0],
+                    missing_issuer: None,
+                }],
+            ),
+            Leaf::Certificates(_, ref bundle) => {
+                (bundle.certificates.iter().collect(), bundle.chains())
+            }
+            _ => return vec![],
+        };
+        let mut findings = vec![];
+        for chain in chains {
+            let path: Vec<&Certificate> = chain
+                .indexes
+                .iter()
+                .map(|&index| certificates[index])
+                .collect();
+            let name = if certificates.len() > 1 {
+                let numbers: Vec<String> = chain
+                    .indexes
+                    .iter()
+                    .map(|index| format!("#{}", index + 1))
+                    .collect();
+                format!("chain {} ", numbers.join(" → "))
+            } else {
+                String::new()
+            };
+            match store.verify(&path, now) {
+                Ok(anchor) if path.len() == 1 && anchor.subject == path[0].subject => (),
+                Ok(anchor) => {
+                    findings.push(format!("✓ {}verifies to trusted {}", name, anchor.name()))
+                }
+                Err(error) => findings.push(format!("🚨 {}doesn't verify: {}", name, error)),
+            }
+        }
+        findings
+    }
+
+    /// Split a multi-key leaf into one `SshKey` leaf per key,
+    /// so each key can be paired and reported on its own
+    pub fn flatten(self) -> Vec<Leaf> {
+        match self {
+            Leaf::SshKeys(path_buf, keys) => keys
+                .into_iter()
+                .map(|key| Leaf::SshKey(path_buf.clone(), key))
+                .collect(),
+            leaf => vec![leaf],
+        }
+    }
 }
 
 impl fmt::Display for Leaf {
@@ -53,31 +458,39 @@ impl fmt::Display for Leaf {
             }
             Leaf::SshKey(ref path_buf, ref key) => {
                 output.push_str(path_buf.to_str().unwrap_or("/"));
-                output.push_str(&format!("\n\t✓ {}", key));
-
-                match key.algorithm {
-                    Algorithm::Rsa(ref modulus) => {
-                        if !key.is_encrypted && modulus.len() < (2048 / 8) {
-                            output.push_str("\n\t⚠️ RSA keys should be 2048 bits or larger");
-                        }
-                    }
-                    Algorithm::Dsa(_) => {
-                        output.push_str("\n\t⚠️ dsa keys are considered insecure");
-                    }
-                    Algorithm::Ecdsa(_, _) => {
-                        output.push_str("\n\t⚠️ ecdsa keys are considered insecure");
-                    }
-                    _ => (),
+                output.push_str(&ssh_key_details(key));
+            }
+            Leaf::SshKeys(ref path_buf, ref keys) => {
+                output.push_str(path_buf.to_str().unwrap_or("/"));
+                output.push_str(&format!("\n\t✓ {} ssh keys in one file", keys.len()));
+                for key in keys {
+                    output.push_str(&ssh_key_details(key));
                 }
-                // TODO figure out how to handle this
-                // if !key.is_public && self.mode.unwrap_or(0o000) & 0o077 != 0o000 {
-                //     output.push_str("\n\t⚠️ insecure permissions");
-                // }
             }
             Leaf::Certificate(ref path_buf, ref certificate) => {
                 output.push_str(path_buf.to_str().unwrap_or("/"));
                 output.push_str(&format!("\n\t✓ {}", certificate));
             }
+            Leaf::Certificates(ref path_buf, ref bundle) => {
+                output.push_str(path_buf.to_str().unwrap_or("/"));
+                output.push_str(&certificates_details(bundle));
+            }
+            Leaf::SshCertificate(ref path_buf, ref certificate) => {
+                output.push_str(path_buf.to_str().unwrap_or("/"));
+                output.push_str(&format!("\n\t✓ {}", certificate));
+            }
+            Leaf::AuthorizedKeys(ref path_buf, ref authorized_keys) => {
+                output.push_str(path_buf.to_str().unwrap_or("/"));
+                output.push_str(&authorized_keys_details(path_buf, authorized_keys));
+            }
+            Leaf::KnownHosts(ref path_buf, ref known_hosts) => {
+                output.push_str(path_buf.to_str().unwrap_or("/"));
+                output.push_str(&known_hosts_details(known_hosts));
+            }
+            Leaf::SshConfig(ref path_buf, ref ssh_config) => {
+                output.push_str(path_buf.to_str().unwrap_or("/"));
+                output.push_str(&ssh_config_details(path_buf, ssh_config));
+            }
             Leaf::Error(ref path_buf, ref message) => {
                 output.push_str(path_buf.to_str().unwrap_or("/"));
                 output.push_str(&format!("\t🚨 Error: {}", message))
@@ -90,7 +503,7 @@ impl fmt::Display for Leaf {
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
-    use super::{Algorithm, Leaf, SshKey};
+    use super::{Algorithm, Leaf, SecurityKey, SshKey};
 
     #[test]
     fn test_leaf_display_encrypted_ed25519() {
@@ -121,6 +534,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_leaf_display_ed25519_sk_private() {
+        let mut security_key = SecurityKey::new(b"ssh:");
+        security_key.flags = Some(0x20);
+        security_key.key_handle_length = Some(64);
+        let ssh_key = SshKey {
+            algorithm: Algorithm::SkEd25519(vec![], security_key),
+            ..Default::default()
+        };
+        let leaf = Leaf::SshKey(PathBuf::from("/unit-test"), ssh_key);
+        assert_eq!(
+            format!("{}", leaf),
+            "/unit-test
+\t✓ private ssh key (ed25519-sk, application ssh:, not encrypted)
+\t⚠️ does not require touching the security key
+\tresident key, stored on the security key
+\tkey handle is 64 bytes
+"
+        );
+    }
+
     #[test]
     fn test_leaf_display_rsa_public() {
         let mut modulus = vec![];
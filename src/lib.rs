@@ -1,13 +1,42 @@
+extern crate aes;
+extern crate aes_gcm;
+extern crate argon2;
 extern crate base64;
+extern crate bcrypt_pbkdf;
+extern crate cbc;
+extern crate chacha20;
+extern crate ctr;
+extern crate des;
+extern crate hex;
+extern crate hmac;
+extern crate md5;
 extern crate nom_pem;
+extern crate num_bigint;
+extern crate pbkdf2;
+extern crate poly1305;
+extern crate ring;
 extern crate rsfs;
+extern crate sha1;
+extern crate sha2;
 extern crate time;
-extern crate x509_parser;
+extern crate zeroize;
+mod asn1;
+pub mod authorized_keys;
 pub mod certificate;
+pub mod cipher;
+pub mod known_hosts;
 pub mod leaf;
+pub mod oids;
+pub mod pkcs8;
+pub mod ppk;
 pub mod private_key;
 pub mod public_key;
+pub mod randomart;
+pub mod ssh_certificate;
+pub mod ssh_config;
 pub mod ssh_key;
+pub mod sshcom;
+pub mod trust_store;
 pub use leaf::Leaf;
 use rsfs::unix_ext::*;
 use rsfs::*;
@@ -17,6 +46,7 @@ use std::path::{Path, PathBuf};
 
 #[macro_use(
     call,
+    count,
     do_parse,
     is_a_s,
     is_a,
@@ -26,6 +56,7 @@ use std::path::{Path, PathBuf};
     length_data,
     map,
     named,
+    recognize,
     tag,
     take,
     error_position
@@ -44,7 +75,20 @@ pub fn scan<
     F: GenFS<Permissions = P, Metadata = M>,
 >(
     fs: &F,
-    path: &AsRef<Path>,
+    path: &dyn AsRef<Path>,
+) -> Result<Leaf, String> {
+    scan_with_passphrase(fs, path, None)
+}
+
+/// Like `scan`, but encrypted private keys get decrypted with `passphrase`
+pub fn scan_with_passphrase<
+    P: Permissions + PermissionsExt,
+    M: Metadata<Permissions = P>,
+    F: GenFS<Permissions = P, Metadata = M>,
+>(
+    fs: &F,
+    path: &dyn AsRef<Path>,
+    passphrase: Option<&[u8]>,
 ) -> Result<Leaf, String> {
     let mut path_buf = PathBuf::new();
     path_buf.push(path);
@@ -72,6 +116,54 @@ pub fn scan<
         return Ok(leaf::Leaf::UnreadableFile(path_buf));
     }
 
+    // ssh_config files are read along with everything they Include
+    if ssh_config::is_ssh_config(path.as_ref()) {
+        let local = ssh_config::Local::from_env();
+        let ssh_config = ssh_config::read(fs, &[path_buf.clone()], &local);
+        return Ok(leaf::Leaf::SshConfig(path_buf, ssh_config));
+    }
+
+    // authorized_keys and known_hosts files can grow past the size limit of single key files
+    let is_authorized_keys = authorized_keys::is_authorized_keys(path.as_ref());
+    if is_authorized_keys || known_hosts::is_known_hosts(path.as_ref()) {
+        let mut bytes = vec![];
+        let read_result = fs
+            .open_file(path)
+            .and_then(|mut file| file.read_to_end(&mut bytes));
+        if let Err(error) = read_result {
+            return Err(format!(
+                "Error opening {}: {}",
+                path.as_ref().display(),
+                error
+            ));
+        }
+        if is_authorized_keys {
+            return Ok(leaf::Leaf::AuthorizedKeys(
+                path_buf,
+                authorized_keys::parse(&bytes),
+            ));
+        }
+        return Ok(leaf::Leaf::KnownHosts(path_buf, known_hosts::parse(&bytes)));
+    }
+
+    // Certificate bundles like ca-bundle.crt hold far more than one key
+    if meta.len() > 4096 && certificate::is_certificate_file(path.as_ref()) {
+        let mut bytes = vec![];
+        if let Err(error) = fs
+            .open_file(path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+        {
+            return Err(format!(
+                "Error opening {}: {}",
+                path.as_ref().display(),
+                error
+            ));
+        }
+        if certificate::has_certificates(&bytes) {
+            return Ok(certificate_leaf(path_buf, &bytes));
+        }
+    }
+
     match meta.len() {
         0...50 => Ok(leaf::Leaf::SmallFile(path_buf)),
         51...4096 => {
@@ -86,22 +178,58 @@ pub fn scan<
             let mut file = open_result.unwrap();
             let mut bytes = vec![];
             file.read_to_end(&mut bytes).unwrap();
-            if bytes.starts_with(b"ssh-") || bytes.starts_with(b"ecdsa-") {
+            let first_word = bytes.split(|&byte| byte == b' ').next().unwrap_or(&[]);
+            if ssh_certificate::is_certificate_type(first_word) {
+                return match ssh_certificate::parse(&bytes) {
+                    Ok(cert) => Ok(leaf::Leaf::SshCertificate(path_buf, cert)),
+                    Err(error) => Ok(leaf::Leaf::Error(path_buf, error)),
+                };
+            }
+            if bytes.starts_with(b"ssh-")
+                || bytes.starts_with(b"ecdsa-")
+                || bytes.starts_with(b"sk-")
+            {
                 return match public_key::parse(&bytes) {
                     Ok(key) => Ok(leaf::Leaf::SshKey(path_buf, key)),
                     Err(error) => Ok(leaf::Leaf::Error(path_buf, error)),
                 };
             }
+            if bytes.starts_with(b"---- BEGIN SSH2 PUBLIC KEY ----") {
+                return match public_key::parse_rfc4716(&bytes) {
+                    Ok(key) => Ok(leaf::Leaf::SshKey(path_buf, key)),
+                    Err(error) => Ok(leaf::Leaf::Error(path_buf, error)),
+                };
+            }
+            if bytes.starts_with(b"-----BEGIN PUBLIC KEY-----")
+                || bytes.starts_with(b"-----BEGIN RSA PUBLIC KEY-----")
+            {
+                return match public_key::parse_pem(&bytes) {
+                    Ok(key) => Ok(leaf::Leaf::SshKey(path_buf, key)),
+                    Err(error) => Ok(leaf::Leaf::Error(path_buf, error)),
+                };
+            }
             if bytes.starts_with(b"-----BEGIN CERTIFICATE----") {
-                return match certificate::parse(&bytes) {
-                    Ok(cert) => Ok(leaf::Leaf::Certificate(path_buf, cert)),
+                return Ok(certificate_leaf(path_buf, &bytes));
+            }
+            if bytes.starts_with(b"---- BEGIN SSH2 ENCRYPTED PRIVATE KEY ----") {
+                return match sshcom::parse(&bytes) {
+                    Ok(key) => Ok(leaf::Leaf::SshKey(path_buf, key)),
+                    Err(error) => Ok(leaf::Leaf::Error(path_buf, error)),
+                };
+            }
+            if bytes.starts_with(b"PuTTY-User-Key-File-") {
+                return match ppk::parse(&bytes, passphrase) {
+                    Ok(key) => Ok(leaf::Leaf::SshKey(path_buf, key)),
                     Err(error) => Ok(leaf::Leaf::Error(path_buf, error)),
                 };
             }
             if bytes.starts_with(b"-----BEGIN ") {
-                match private_key::parse(&bytes) {
-                    Ok(key) => {
-                        return Ok(leaf::Leaf::SshKey(path_buf, key));
+                match private_key::parse(&bytes, passphrase) {
+                    Ok(mut keys) => {
+                        if keys.len() == 1 {
+                            return Ok(leaf::Leaf::SshKey(path_buf, keys.remove(0)));
+                        }
+                        return Ok(leaf::Leaf::SshKeys(path_buf, keys));
                     }
                     Err(message) => {
                         return Err(message);
@@ -114,3 +242,16 @@ pub fn scan<
         _ => Ok(leaf::Leaf::LargeFile(path_buf)),
     }
 }
+
+// One certificate, or a bundle when the file holds more
+fn certificate_leaf(path_buf: PathBuf, bytes: &[u8]) -> leaf::Leaf {
+    match certificate::parse_bundle(bytes) {
+        Ok(mut bundle) => {
+            if bundle.certificates.len() == 1 && bundle.errors.is_empty() {
+                return leaf::Leaf::Certificate(path_buf, bundle.certificates.remove(0));
+            }
+            leaf::Leaf::Certificates(path_buf, bundle)
+        }
+        Err(error) => leaf::Leaf::Error(path_buf, error),
+    }
+}
@@ -1,12 +1,21 @@
+extern crate rpassword;
 extern crate rsfs;
 extern crate structopt;
 #[macro_use(StructOpt)]
 extern crate structopt_derive;
 extern crate tealeaves;
+extern crate time;
+extern crate zeroize;
+use rsfs::{GenFS, Metadata};
+use std::io::Read;
 use std::path::PathBuf;
 use std::{env, fs, io};
 use structopt::StructOpt;
+use tealeaves::known_hosts::{self, Marker};
 use tealeaves::leaf::Leaf;
+use tealeaves::ssh_config;
+use tealeaves::trust_store::{self, TrustStore};
+use zeroize::Zeroizing;
 
 #[derive(StructOpt, Debug)]
 #[structopt(
@@ -19,18 +28,183 @@ struct Opt {
         parse(from_os_str)
     )]
     paths: Vec<PathBuf>,
+    #[structopt(
+        long = "randomart",
+        help = "Print the OpenSSH randomart box under each ssh key"
+    )]
+    randomart: bool,
+    #[structopt(
+        long = "min-kdf-rounds",
+        default_value = "16",
+        help = "Warn about encrypted ssh keys with fewer bcrypt kdf rounds than this"
+    )]
+    min_kdf_rounds: u32,
+    #[structopt(
+        long = "ask-passphrase",
+        help = "Prompt for the passphrase of each encrypted private key"
+    )]
+    ask_passphrase: bool,
+    #[structopt(
+        long = "passphrase-env",
+        help = "Decrypt private keys with the passphrase in this environment variable"
+    )]
+    passphrase_env: Option<String>,
+    #[structopt(
+        long = "passphrase-file",
+        help = "Decrypt private keys with the passphrase in this file",
+        parse(from_os_str)
+    )]
+    passphrase_file: Option<PathBuf>,
+    #[structopt(
+        long = "maintain-known-hosts",
+        help = "Hash, dedupe and prune the given known_hosts files (default ~/.ssh/known_hosts), keeping a .old backup"
+    )]
+    maintain_known_hosts: bool,
+    #[structopt(
+        long = "ban-key-types",
+        default_value = "ssh-dss",
+        help = "Comma-separated host key types --maintain-known-hosts drops"
+    )]
+    ban_key_types: String,
+    #[structopt(
+        long = "trust-store",
+        default_value = "/etc/ssl/certs",
+        help = "Verify certificate chains against the certificates in this directory or bundle",
+        parse(from_os_str)
+    )]
+    trust_store: PathBuf,
 }
 
 type LeafResults = Vec<Result<Leaf, String>>;
 
+fn print_randomart(leaf: &Leaf) {
+    if let Leaf::SshKey(_, ref key) = *leaf {
+        if let Some(art) = key.randomart() {
+            for line in art.lines() {
+                println!("\t{}", line);
+            }
+        }
+    }
+}
+
+// Passphrase given up front via --passphrase-env or --passphrase-file
+fn read_passphrase(opt: &Opt) -> io::Result<Option<Zeroizing<String>>> {
+    if let Some(ref name) = opt.passphrase_env {
+        return match env::var(name) {
+            Ok(passphrase) => Ok(Some(Zeroizing::new(passphrase))),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Environment variable {} is not set", name),
+            )),
+        };
+    }
+    if let Some(ref path) = opt.passphrase_file {
+        let mut passphrase = Zeroizing::new(fs::read_to_string(path)?);
+        // The trailing newline is not part of the passphrase
+        let length = passphrase.trim_end_matches(['\n', '\r']).len();
+        passphrase.truncate(length);
+        return Ok(Some(passphrase));
+    }
+    Ok(None)
+}
+
+fn is_encrypted(leaf: &Leaf) -> bool {
+    match *leaf {
+        Leaf::SshKey(_, ref key) => key.is_encrypted && !key.is_decrypted,
+        Leaf::SshKeys(_, ref keys) => keys.iter().any(|key| key.is_encrypted && !key.is_decrypted),
+        _ => false,
+    }
+}
+
+// Which keys the ssh_config files use, and which host tries which key first
+fn print_key_usage(config_paths: &[PathBuf], leaves: &[Leaf]) {
+    let fs = rsfs::disk::FS;
+    let local = ssh_config::Local::from_env();
+    let config = ssh_config::read(&fs, config_paths, &local);
+    let usage = ssh_config::key_usage(&fs, &config, &local, leaves);
+    if usage.missing.is_empty() && usage.unused.is_empty() && usage.first_keys.is_empty() {
+        return;
+    }
+    let names: Vec<String> = config_paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    println!("{}", names.join(", "));
+    println!(
+        "\t✓ ssh_config with {} host aliases",
+        usage.first_keys.len()
+    );
+    for reference in &usage.missing {
+        println!(
+            "\t{} line {}: ⚠️ {} {} does not exist",
+            reference.file.display(),
+            reference.line,
+            reference.keyword,
+            reference.path.display()
+        );
+    }
+    for path in &usage.unused {
+        println!(
+            "\t⚠️ private key at {} is not used by any IdentityFile",
+            path.display()
+        );
+    }
+    for (alias, first) in &usage.first_keys {
+        match *first {
+            Some(ref path) => println!("\t✓ {} tries {} first", alias, path.display()),
+            None => println!("\t⚠️ {} finds no identity file", alias),
+        }
+    }
+    println!();
+}
+
+// Rewrite known_hosts files instead of reporting on them
+fn maintain_known_hosts(opt: &Opt) -> io::Result<()> {
+    let mut paths = opt.paths.clone();
+    if paths.is_empty() {
+        if let Some(home) = env::home_dir() {
+            paths.push(home.join(".ssh").join("known_hosts"));
+        }
+    }
+    let maintenance = known_hosts::Maintenance {
+        banned_key_types: opt
+            .ban_key_types
+            .split(',')
+            .map(|key_type| key_type.trim().to_owned())
+            .filter(|key_type| !key_type.is_empty())
+            .collect(),
+        ..Default::default()
+    };
+    for path in &paths {
+        match known_hosts::maintain(&rsfs::disk::FS, path, &maintenance) {
+            Ok(ref report) if report.is_unchanged() => println!("{}\n\t✓ unchanged", path.display()),
+            Ok(report) => println!(
+                "{}\n\t✓ {} hosts hashed, {} duplicate entries merged, {} banned entries dropped\n\tprevious version kept as {}.old",
+                path.display(),
+                report.hashed,
+                report.merged,
+                report.dropped,
+                path.display()
+            ),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+    Ok(())
+}
+
 fn tealeaves() -> io::Result<()> {
     let opt = Opt::from_args();
+    if opt.maintain_known_hosts {
+        return maintain_known_hosts(&opt);
+    }
+    let passphrase = read_passphrase(&opt)?;
     let fs = rsfs::disk::FS;
 
     // Gather the list of paths we will inspect
     // either command line args or by listing ~/.ssh
     let mut paths: Vec<PathBuf> = opt.paths;
-    if paths.is_empty() {
+    let scanning_dot_ssh = paths.is_empty();
+    if scanning_dot_ssh {
         // If no paths on command line, scan ~/.ssh
         match env::home_dir() {
             Some(home) => {
@@ -42,9 +216,30 @@ fn tealeaves() -> io::Result<()> {
             None => eprintln!("Error determining your home directory via HOME env var"),
         }
     }
-    // Scan all the paths
-    let results: Vec<Result<tealeaves::Leaf, String>> =
-        paths.iter().map(|p| tealeaves::scan(&fs, &p)).collect();
+    // Scan all the paths, decrypting private keys if we have a passphrase
+    let mut results: Vec<Result<tealeaves::Leaf, String>> = vec![];
+    for path in &paths {
+        let mut result = tealeaves::scan(&fs, &path);
+        let needs_passphrase = match result {
+            Ok(ref leaf) => is_encrypted(leaf),
+            Err(_) => false,
+        };
+        if needs_passphrase {
+            let passphrase = if opt.ask_passphrase {
+                let prompt = format!("Passphrase for {}: ", path.display());
+                Some(Zeroizing::new(rpassword::prompt_password(prompt)?))
+            } else {
+                passphrase.clone()
+            };
+            if let Some(passphrase) = passphrase {
+                match tealeaves::scan_with_passphrase(&fs, &path, Some(passphrase.as_bytes())) {
+                    Ok(leaf) => result = Ok(leaf),
+                    Err(error) => eprintln!("{}: {}", path.display(), error),
+                }
+            }
+        }
+        results.push(result);
+    }
 
     // Split the results apart into Err and Ok
     let (errors, oks): (LeafResults, LeafResults) = results.into_iter().partition(|r| r.is_err());
@@ -57,7 +252,8 @@ fn tealeaves() -> io::Result<()> {
     }
 
     // Unwrap all the Oks so we don't need to deal with Result any more
-    let leaves: Vec<Leaf> = oks.into_iter().map(|r| r.unwrap()).collect();
+    // and give each key of a multi-key file its own leaf
+    let leaves: Vec<Leaf> = oks.into_iter().flat_map(|r| r.unwrap().flatten()).collect();
 
     // Split into public keys and all other variants
     // so we can match public/private pairs together
@@ -66,26 +262,171 @@ fn tealeaves() -> io::Result<()> {
         _ => false,
     });
 
+    // known_hosts files can vouch for certificate authorities
+    let mut known_hosts_paths: Vec<PathBuf> = paths
+        .iter()
+        .filter(|path| known_hosts::is_known_hosts(path))
+        .cloned()
+        .collect();
+    if let Some(home) = env::home_dir() {
+        let default = home.join(".ssh").join("known_hosts");
+        let is_file = fs
+            .metadata(&default)
+            .map(|metadata| metadata.is_file())
+            .unwrap_or(false);
+        if is_file && !known_hosts_paths.contains(&default) {
+            known_hosts_paths.push(default);
+        }
+    }
+    let mut authorities: Vec<(PathBuf, Vec<u8>)> = vec![];
+    for path in known_hosts_paths {
+        let mut bytes = vec![];
+        if fs
+            .open_file(&path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .is_err()
+        {
+            continue;
+        }
+        for entry in known_hosts::parse(&bytes).entries {
+            if entry.marker == Some(Marker::CertAuthority) {
+                authorities.push((path.clone(), entry.key.public_blob));
+            }
+        }
+    }
+
+    // Only read the trust store when there are certificates to verify
+    let has_certificates = others
+        .iter()
+        .any(|leaf| matches!(*leaf, Leaf::Certificate(..) | Leaf::Certificates(..)));
+    let trust_store: Option<TrustStore> = if has_certificates {
+        match trust_store::load(&rsfs::disk::FS, &opt.trust_store) {
+            Ok(store) => Some(store),
+            Err(error) => {
+                eprintln!("Not verifying certificate chains: {}", error);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let now = time::now_utc();
+    let print_trust = |leaf: &Leaf| {
+        if let Some(ref store) = trust_store {
+            for finding in leaf.trust_findings(store, &now) {
+                println!("\t{}", finding);
+            }
+        }
+    };
+
     // Print out everything except public keys
-    for leaf in others {
-        match leaf {
+    for leaf in &others {
+        match *leaf {
             Leaf::SshKey(ref _pb, ref key) => {
                 print!("{}", leaf);
+                if key.has_weak_kdf(opt.min_kdf_rounds) {
+                    println!(
+                        "\t⚠️ fewer than {} kdf rounds, re-protect with ssh-keygen -p -a {}",
+                        opt.min_kdf_rounds, opt.min_kdf_rounds
+                    );
+                }
+                if opt.randomart {
+                    print_randomart(leaf);
+                }
                 if !key.is_public {
                     let pair = publics.iter().find(|pub_leaf| match *(*pub_leaf) {
                         Leaf::SshKey(ref _pb, ref pub_key) => pub_key.is_pair(key),
                         _ => false,
                     });
-                    match pair {
-                        Some(pub_key) => match *pub_key {
-                            Leaf::SshKey(ref path, _) => {
-                                println!("\tpairs with public key at: {}\n", path.display());
+                    if let Some(Leaf::SshKey(path, _)) = pair {
+                        println!("\tpairs with public key at: {}", path.display());
+                    }
+                    for other in &others {
+                        match *other {
+                            Leaf::SshCertificate(ref path, ref cert) if cert.certifies(key) => {
+                                println!("\tcertified by certificate at: {}", path.display())
+                            }
+                            Leaf::Certificate(ref path, ref cert) if cert.pairs_with(key) => {
+                                println!("\tpairs with x509 certificate at: {}", path.display())
                             }
-                            _ => println!("\n"),
-                        },
-                        _ => println!(),
+                            Leaf::Certificates(ref path, ref bundle) => {
+                                for (index, cert) in bundle.certificates.iter().enumerate() {
+                                    if cert.pairs_with(key) {
+                                        println!(
+                                            "\tpairs with x509 certificate #{} at: {}",
+                                            index + 1,
+                                            path.display()
+                                        );
+                                    }
+                                }
+                            }
+                            _ => (),
+                        }
                     }
                 }
+                println!();
+            }
+            Leaf::SshCertificate(ref _pb, ref cert) => {
+                print!("{}", leaf);
+                for other in &others {
+                    if let Leaf::SshKey(ref path, ref key) = *other {
+                        if cert.certifies(key) {
+                            println!("\tcertifies private key at: {}", path.display());
+                        }
+                    }
+                }
+                let mut ca_found = false;
+                for other in others.iter().chain(publics.iter()) {
+                    if let Leaf::SshKey(ref path, ref key) = *other {
+                        if cert.is_signed_by(key) {
+                            ca_found = true;
+                            println!("\tsigning CA key at: {}", path.display());
+                        }
+                    }
+                }
+                for (path, blob) in &authorities {
+                    if *blob == cert.signature_key.public_blob {
+                        ca_found = true;
+                        println!(
+                            "\tsigning CA key is a @cert-authority in: {}",
+                            path.display()
+                        );
+                    }
+                }
+                if !ca_found {
+                    println!("\t⚠️ signing CA key not found");
+                }
+                println!();
+            }
+            Leaf::Certificate(ref _pb, ref cert) => {
+                print!("{}", leaf);
+                print_trust(leaf);
+                for other in &others {
+                    if let Leaf::SshKey(ref path, ref key) = *other {
+                        if cert.pairs_with(key) {
+                            println!("\tpairs with private key at: {}", path.display());
+                        }
+                    }
+                }
+                println!();
+            }
+            Leaf::Certificates(ref _pb, ref bundle) => {
+                print!("{}", leaf);
+                print_trust(leaf);
+                for (index, cert) in bundle.certificates.iter().enumerate() {
+                    for other in &others {
+                        if let Leaf::SshKey(ref path, ref key) = *other {
+                            if cert.pairs_with(key) {
+                                println!(
+                                    "\t#{} pairs with private key at: {}",
+                                    index + 1,
+                                    path.display()
+                                );
+                            }
+                        }
+                    }
+                }
+                println!();
             }
             _ => println!("{}", leaf),
         }
@@ -93,7 +434,33 @@ fn tealeaves() -> io::Result<()> {
 
     // print out the public keys
     for public_key in publics {
-        println!("{}", public_key);
+        print!("{}", public_key);
+        if opt.randomart {
+            print_randomart(&public_key);
+        }
+        println!();
+    }
+
+    // The ssh_config files given, or when scanning ~/.ssh
+    // ~/.ssh/config and /etc/ssh/ssh_config, in the order ssh reads them
+    let mut config_paths: Vec<PathBuf> = paths
+        .iter()
+        .filter(|path| ssh_config::is_ssh_config(path))
+        .cloned()
+        .collect();
+    if scanning_dot_ssh {
+        let mut defaults = vec![PathBuf::from("/etc/ssh/ssh_config")];
+        if let Some(home) = env::home_dir() {
+            defaults.insert(0, home.join(".ssh").join("config"));
+        }
+        for default in defaults {
+            if default.is_file() && !config_paths.contains(&default) {
+                config_paths.push(default);
+            }
+        }
+    }
+    if !config_paths.is_empty() {
+        print_key_usage(&config_paths, &others);
     }
 
     Ok(())
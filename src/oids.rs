@@ -0,0 +1,183 @@
+// Names for the object identifiers found in keys and certificates
+
+// Dotted OID and the name OpenSSL and the RFCs give it. Certificate
+// name attributes keep their short forms, like CN and O.
+const NAMES: &[(&str, &str)] = &[
+    // Signature algorithms
+    ("1.2.840.113549.1.1.2", "md2WithRSAEncryption"),
+    ("1.2.840.113549.1.1.4", "md5WithRSAEncryption"),
+    ("1.2.840.113549.1.1.5", "sha1WithRSAEncryption"),
+    ("1.2.840.113549.1.1.10", "rsassaPss"),
+    ("1.2.840.113549.1.1.11", "sha256WithRSAEncryption"),
+    ("1.2.840.113549.1.1.12", "sha384WithRSAEncryption"),
+    ("1.2.840.113549.1.1.13", "sha512WithRSAEncryption"),
+    ("1.2.840.113549.1.1.14", "sha224WithRSAEncryption"),
+    ("1.2.840.10045.4.1", "ecdsa-with-SHA1"),
+    ("1.2.840.10045.4.3.1", "ecdsa-with-SHA224"),
+    ("1.2.840.10045.4.3.2", "ecdsa-with-SHA256"),
+    ("1.2.840.10045.4.3.3", "ecdsa-with-SHA384"),
+    ("1.2.840.10045.4.3.4", "ecdsa-with-SHA512"),
+    ("1.2.840.10040.4.3", "dsa-with-SHA1"),
+    ("2.16.840.1.101.3.4.3.1", "dsa-with-SHA224"),
+    ("2.16.840.1.101.3.4.3.2", "dsa-with-SHA256"),
+    ("1.3.101.112", "Ed25519"),
+    ("1.3.101.113", "Ed448"),
+    // Public key algorithms
+    ("1.2.840.113549.1.1.1", "rsaEncryption"),
+    ("1.2.840.10045.2.1", "id-ecPublicKey"),
+    ("1.2.840.10040.4.1", "dsa"),
+    ("1.3.101.110", "X25519"),
+    ("1.3.101.111", "X448"),
+    // EC curves
+    ("1.2.840.10045.3.1.1", "P-192"),
+    ("1.3.132.0.33", "P-224"),
+    ("1.2.840.10045.3.1.7", "P-256"),
+    ("1.3.132.0.34", "P-384"),
+    ("1.3.132.0.35", "P-521"),
+    ("1.3.132.0.10", "secp256k1"),
+    ("1.3.36.3.3.2.8.1.1.7", "brainpoolP256r1"),
+    ("1.3.36.3.3.2.8.1.1.11", "brainpoolP384r1"),
+    ("1.3.36.3.3.2.8.1.1.13", "brainpoolP512r1"),
+    // Hashes and the RSA-PSS mask generation function
+    ("1.2.840.113549.2.2", "md2"),
+    ("1.2.840.113549.2.5", "md5"),
+    ("1.3.14.3.2.26", "sha1"),
+    ("2.16.840.1.101.3.4.2.4", "sha224"),
+    ("2.16.840.1.101.3.4.2.1", "sha256"),
+    ("2.16.840.1.101.3.4.2.2", "sha384"),
+    ("2.16.840.1.101.3.4.2.3", "sha512"),
+    ("1.2.840.113549.1.1.8", "mgf1"),
+    // Certificate extensions
+    ("2.5.29.14", "subjectKeyIdentifier"),
+    ("2.5.29.15", "keyUsage"),
+    ("2.5.29.17", "subjectAltName"),
+    ("2.5.29.18", "issuerAltName"),
+    ("2.5.29.19", "basicConstraints"),
+    ("2.5.29.30", "nameConstraints"),
+    ("2.5.29.31", "cRLDistributionPoints"),
+    ("2.5.29.32", "certificatePolicies"),
+    ("2.5.29.33", "policyMappings"),
+    ("2.5.29.35", "authorityKeyIdentifier"),
+    ("2.5.29.36", "policyConstraints"),
+    ("2.5.29.37", "extendedKeyUsage"),
+    ("2.5.29.54", "inhibitAnyPolicy"),
+    ("1.3.6.1.5.5.7.1.1", "authorityInfoAccess"),
+    ("1.3.6.1.5.5.7.1.11", "subjectInfoAccess"),
+    ("1.3.6.1.5.5.7.1.24", "tlsFeature"),
+    ("1.3.6.1.4.1.11129.2.4.2", "signedCertificateTimestampList"),
+    ("1.3.6.1.4.1.11129.2.4.3", "ctPrecertificatePoison"),
+    ("2.16.840.1.113730.1.1", "nsCertType"),
+    ("2.16.840.1.113730.1.13", "nsComment"),
+    // extendedKeyUsage purposes
+    ("1.3.6.1.5.5.7.3.1", "serverAuth"),
+    ("1.3.6.1.5.5.7.3.2", "clientAuth"),
+    ("1.3.6.1.5.5.7.3.3", "codeSigning"),
+    ("1.3.6.1.5.5.7.3.4", "emailProtection"),
+    ("1.3.6.1.5.5.7.3.8", "timeStamping"),
+    ("1.3.6.1.5.5.7.3.9", "OCSPSigning"),
+    ("2.5.29.37.0", "anyExtendedKeyUsage"),
+    // Name attributes
+    ("2.5.4.3", "CN"),
+    ("2.5.4.4", "SN"),
+    ("2.5.4.5", "serialNumber"),
+    ("2.5.4.6", "C"),
+    ("2.5.4.7", "L"),
+    ("2.5.4.8", "ST"),
+    ("2.5.4.9", "street"),
+    ("2.5.4.10", "O"),
+    ("2.5.4.11", "OU"),
+    ("2.5.4.12", "title"),
+    ("2.5.4.17", "postalCode"),
+    ("2.5.4.42", "GN"),
+    ("2.5.4.97", "organizationIdentifier"),
+    ("1.2.840.113549.1.9.1", "emailAddress"),
+    ("1.3.6.1.4.1.311.60.2.1.1", "jurisdictionL"),
+    ("1.3.6.1.4.1.311.60.2.1.2", "jurisdictionST"),
+    ("1.3.6.1.4.1.311.60.2.1.3", "jurisdictionC"),
+    ("2.5.4.15", "businessCategory"),
+    ("0.9.2342.19200300.100.1.25", "DC"),
+    ("0.9.2342.19200300.100.1.1", "UID"),
+    // Certificate policies
+    ("2.5.29.32.0", "anyPolicy"),
+    ("2.23.140.1.1", "ev-guidelines"),
+    ("2.23.140.1.2.1", "domain-validated"),
+    ("2.23.140.1.2.2", "organization-validated"),
+    ("2.23.140.1.2.3", "individual-validated"),
+];
+
+pub fn name(oid: &str) -> Option<&'static str> {
+    NAMES
+        .iter()
+        .find(|&&(dotted, _)| dotted == oid)
+        .map(|&(_, name)| name)
+}
+
+/// The name, or the dotted OID itself when it isn't in the registry
+pub fn describe(oid: &str) -> String {
+    name(oid).unwrap_or(oid).to_owned()
+}
+
+/// The hash named in a signature algorithm. None for RSA-PSS, which
+/// keeps its hash in the parameters, and for the EdDSA algorithms.
+pub fn signature_hash(oid: &str) -> Option<&'static str> {
+    match oid {
+        "1.2.840.113549.1.1.2" => Some("md2"),
+        "1.2.840.113549.1.1.4" => Some("md5"),
+        "1.2.840.113549.1.1.5" | "1.2.840.10045.4.1" | "1.2.840.10040.4.3" => Some("sha1"),
+        "1.2.840.113549.1.1.14" | "1.2.840.10045.4.3.1" | "2.16.840.1.101.3.4.3.1" => {
+            Some("sha224")
+        }
+        "1.2.840.113549.1.1.11" | "1.2.840.10045.4.3.2" | "2.16.840.1.101.3.4.3.2" => {
+            Some("sha256")
+        }
+        "1.2.840.113549.1.1.12" | "1.2.840.10045.4.3.3" => Some("sha384"),
+        "1.2.840.113549.1.1.13" | "1.2.840.10045.4.3.4" => Some("sha512"),
+        _ => None,
+    }
+}
+
+/// Hashes with practical collision attacks, so signatures over them can be forged
+pub fn is_weak_hash(hash: &str) -> bool {
+    matches!(hash, "md2" | "md5" | "sha1")
+}
+
+/// Output length in bytes, which RSA-PSS salts usually match
+pub fn hash_length(hash: &str) -> Option<u32> {
+    match hash {
+        "md2" | "md5" => Some(16),
+        "sha1" => Some(20),
+        "sha224" => Some(28),
+        "sha256" => Some(32),
+        "sha384" => Some(48),
+        "sha512" => Some(64),
+        _ => None,
+    }
+}
+
+/// The OpenSSH name of the curves ssh keys can use, nistp256 for P-256
+pub fn ssh_curve(oid: &str) -> Option<String> {
+    match name(oid)? {
+        curve @ "P-256" | curve @ "P-384" | curve @ "P-521" => {
+            Some(format!("nistp{}", &curve[2..]))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe, name, signature_hash, ssh_curve, NAMES};
+
+    #[test]
+    fn test_names() {
+        assert_eq!(name("1.2.840.10045.4.3.2"), Some("ecdsa-with-SHA256"));
+        assert_eq!(describe("1.2.3.4"), "1.2.3.4");
+        assert_eq!(signature_hash("1.2.840.10045.4.1"), Some("sha1"));
+        assert_eq!(ssh_curve("1.3.132.0.35"), Some("nistp521".into()));
+        assert_eq!(ssh_curve("1.3.132.0.10"), None);
+        // Every OID once
+        for (index, &(oid, _)) in NAMES.iter().enumerate() {
+            assert!(!NAMES[..index].iter().any(|&(earlier, _)| earlier == oid));
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// PKCS#8 "PRIVATE KEY" and "ENCRYPTED PRIVATE KEY", what modern OpenSSL writes
+// https://tools.ietf.org/html/rfc5208 https://tools.ietf.org/html/rfc8018
+use asn1::{algorithm_identifier, der, mpint, oid, sequence, slice};
+use cipher;
+use der_parser::DerObject;
+use num_bigint::BigUint;
+use oids::ssh_curve;
+use pbkdf2::pbkdf2_hmac;
+use private_key::rsa_private;
+use public_key::{dsa_public, ecdsa_public, ed25519_public};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use sha1::Sha1;
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+use ssh_key::{Algorithm, Kdf, SshKey};
+use std::convert::TryFrom;
+use zeroize::Zeroizing;
+
+// FIPS 186-4 sizes, the largest DSA keys anything generates
+const MAX_DSA_P_BITS: u64 = 3072;
+const MAX_DSA_Q_BITS: u64 = 256;
+
+// Dss-Parms are p, q, g and the private key is just x, so y = g^x mod p
+fn dsa_private(
+    parameters: Option<&DerObject>,
+    private: &[u8],
+) -> Result<(Algorithm, Vec<u8>), String> {
+    let parameters = match parameters {
+        Some(parameters) => sequence(parameters)?,
+        None => return Err("DSA key without parameters".into()),
+    };
+    if parameters.len() != 3 {
+        return Err("Invalid DSA parameters".into());
+    }
+    let p_integer = slice(&parameters[0])?;
+    let q_integer = slice(&parameters[1])?;
+    let g_integer = slice(&parameters[2])?;
+    let x_integer = der(private)?;
+    let p = BigUint::from_bytes_be(p_integer);
+    let q = BigUint::from_bytes_be(q_integer);
+    let g = BigUint::from_bytes_be(g_integer);
+    let x = BigUint::from_bytes_be(slice(&x_integer)?);
+    // The modulus and exponent come from the file, so keep modpow cheap and defined
+    if p <= BigUint::from(1u32) || p.bits() > MAX_DSA_P_BITS || q.bits() > MAX_DSA_Q_BITS {
+        return Err("Invalid DSA parameters".into());
+    }
+    if g >= p || x >= q {
+        return Err("Invalid DSA private key".into());
+    }
+    let y = g.modpow(&x, &p);
+    Ok(dsa_public(
+        p_integer,
+        q_integer,
+        g_integer,
+        &mpint(&y.to_bytes_be()),
+    ))
+}
+
+/*
+ECPrivateKey ::= SEQUENCE {
+  version        INTEGER { ecPrivkeyVer1(1) } (ecPrivkeyVer1),
+  privateKey     OCTET STRING,
+  parameters [0] ECParameters {{ NamedCurve }} OPTIONAL,
+  publicKey  [1] BIT STRING OPTIONAL
+}
+The curve comes from the PKCS#8 AlgorithmIdentifier instead of [0]
+*/
+fn ecdsa_private(
+    parameters: Option<&DerObject>,
+    private: &[u8],
+) -> Result<(Algorithm, Vec<u8>), String> {
+    let curve_oid = match parameters {
+        Some(parameters) => oid(parameters)?,
+        None => return Err("EC key without a named curve".into()),
+    };
+    let curve = match ssh_curve(&curve_oid) {
+        Some(curve) => curve,
+        None => return Ok((Algorithm::Unknown, vec![])),
+    };
+    let ec_private_key = der(private)?;
+    let public_key = sequence(&ec_private_key)?
+        .iter()
+        .find(|item| item.is_contextspecific() && item.tag == 1);
+    match public_key {
+        Some(public_key) => Ok(ecdsa_public(&curve, slice(&der(slice(public_key)?)?)?)),
+        None => Err("EC private key without its public key".into()),
+    }
+}
+
+// RFC 8410: the private key is an OCTET STRING holding the 32 byte seed
+fn ed25519_private(private: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
+    let seed = der(private)?;
+    let key_pair = Ed25519KeyPair::from_seed_unchecked(slice(&seed)?)
+        .map_err(|error| format!("Ed25519 error: {}", error))?;
+    Ok(ed25519_public(key_pair.public_key().as_ref()))
+}
+
+/*
+PrivateKeyInfo ::= SEQUENCE {
+  version                   Version,
+  privateKeyAlgorithm       AlgorithmIdentifier,
+  privateKey                OCTET STRING,
+  attributes           [0]  IMPLICIT Attributes OPTIONAL
+}
+*/
+fn private_key_info(input: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
+    let info = der(input)?;
+    let items = sequence(&info)?;
+    if items.len() < 3 {
+        return Err("Invalid PKCS#8 PrivateKeyInfo".into());
+    }
+    let (algorithm, parameters) = algorithm_identifier(&items[1])?;
+    let private = slice(&items[2])?;
+    match algorithm.as_str() {
+        "1.2.840.113549.1.1.1" => rsa_private(private),
+        "1.2.840.10040.4.1" => dsa_private(parameters, private),
+        "1.2.840.10045.2.1" => ecdsa_private(parameters, private),
+        "1.3.101.112" => ed25519_private(private),
+        "1.3.101.110" => Ok((Algorithm::X25519, vec![])),
+        _ => Ok((Algorithm::Unknown, vec![])),
+    }
+}
+
+fn prf_name(oid: &str) -> Option<&'static str> {
+    match oid {
+        "1.2.840.113549.2.7" => Some("hmacWithSHA1"),
+        "1.2.840.113549.2.8" => Some("hmacWithSHA224"),
+        "1.2.840.113549.2.9" => Some("hmacWithSHA256"),
+        "1.2.840.113549.2.10" => Some("hmacWithSHA384"),
+        "1.2.840.113549.2.11" => Some("hmacWithSHA512"),
+        _ => None,
+    }
+}
+
+// PBES2 encryption schemes by OpenSSL name and the name cipher.rs knows them by
+fn encryption_scheme(oid: &str) -> Option<(&'static str, Option<&'static str>)> {
+    match oid {
+        "2.16.840.1.101.3.4.1.2" => Some(("aes-128-cbc", Some("aes128-cbc"))),
+        "2.16.840.1.101.3.4.1.22" => Some(("aes-192-cbc", Some("aes192-cbc"))),
+        "2.16.840.1.101.3.4.1.42" => Some(("aes-256-cbc", Some("aes256-cbc"))),
+        "1.2.840.113549.3.7" => Some(("des-ede3-cbc", Some("3des-cbc"))),
+        "1.3.14.3.2.7" => Some(("des-cbc", None)),
+        _ => None,
+    }
+}
+
+fn pbes1_name(oid: &str) -> Option<&'static str> {
+    match oid {
+        "1.2.840.113549.1.5.1" => Some("pbeWithMD2AndDES-CBC"),
+        "1.2.840.113549.1.5.3" => Some("pbeWithMD5AndDES-CBC"),
+        "1.2.840.113549.1.5.4" => Some("pbeWithMD2AndRC2-CBC"),
+        "1.2.840.113549.1.5.6" => Some("pbeWithMD5AndRC2-CBC"),
+        "1.2.840.113549.1.5.10" => Some("pbeWithSHA1AndDES-CBC"),
+        "1.2.840.113549.1.5.11" => Some("pbeWithSHA1AndRC2-CBC"),
+        "1.2.840.113549.1.12.1.1" => Some("pbeWithSHAAnd128BitRC4"),
+        "1.2.840.113549.1.12.1.2" => Some("pbeWithSHAAnd40BitRC4"),
+        "1.2.840.113549.1.12.1.3" => Some("pbeWithSHAAnd3-KeyTripleDES-CBC"),
+        "1.2.840.113549.1.12.1.4" => Some("pbeWithSHAAnd2-KeyTripleDES-CBC"),
+        "1.2.840.113549.1.12.1.5" => Some("pbeWithSHAAnd128BitRC2-CBC"),
+        "1.2.840.113549.1.12.1.6" => Some("pbeWithSHAAnd40BitRC2-CBC"),
+        _ => None,
+    }
+}
+
+fn pbkdf2(
+    prf: &str,
+    passphrase: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    key: &mut [u8],
+) -> Result<(), String> {
+    match prf {
+        "hmacWithSHA1" => pbkdf2_hmac::<Sha1>(passphrase, salt, iterations, key),
+        "hmacWithSHA224" => pbkdf2_hmac::<Sha224>(passphrase, salt, iterations, key),
+        "hmacWithSHA256" => pbkdf2_hmac::<Sha256>(passphrase, salt, iterations, key),
+        "hmacWithSHA384" => pbkdf2_hmac::<Sha384>(passphrase, salt, iterations, key),
+        "hmacWithSHA512" => pbkdf2_hmac::<Sha512>(passphrase, salt, iterations, key),
+        _ => return Err(format!("Unsupported PBKDF2 PRF {}", prf)),
+    }
+    Ok(())
+}
+
+/// Everything needed to decrypt a PBES2 key, besides the passphrase
+struct Pbes2<'a> {
+    kdf: Kdf,
+    salt: &'a [u8],
+    cipher_name: String,
+    decrypt_name: Option<&'static str>,
+    iv: &'a [u8],
+}
+
+/*
+PBES2-params ::= SEQUENCE {
+  keyDerivationFunc AlgorithmIdentifier {{PBES2-KDFs}},
+  encryptionScheme AlgorithmIdentifier {{PBES2-Encs}}
+}
+PBKDF2-params ::= SEQUENCE {
+  salt OCTET STRING,
+  iterationCount INTEGER (1..MAX),
+  keyLength INTEGER (1..MAX) OPTIONAL,
+  prf AlgorithmIdentifier {{PBKDF2-PRFs}} DEFAULT algid-hmacWithSHA1
+}
+scrypt-params ::= SEQUENCE {
+  salt OCTET STRING,
+  costParameter INTEGER (1..MAX),
+  blockSize INTEGER (1..MAX),
+  parallelizationParameter INTEGER (1..MAX),
+  keyLength INTEGER (1..MAX) OPTIONAL
+}
+*/
+fn pbes2_parameters<'a>(parameters: Option<&DerObject<'a>>) -> Result<Pbes2<'a>, String> {
+    let items = match parameters {
+        Some(parameters) => sequence(parameters)?,
+        None => return Err("PBES2 without parameters".into()),
+    };
+    if items.len() != 2 {
+        return Err("Invalid PBES2 parameters".into());
+    }
+    let (kdf_oid, kdf_parameters) = algorithm_identifier(&items[0])?;
+    let kdf_items = match kdf_parameters {
+        Some(kdf_parameters) => sequence(kdf_parameters)?,
+        None => return Err("Key derivation without parameters".into()),
+    };
+    if kdf_items.len() < 2 {
+        return Err("Invalid key derivation parameters".into());
+    }
+    let salt = slice(&kdf_items[0])?;
+    let integer = |index: usize| -> Result<u64, String> {
+        match kdf_items.get(index) {
+            Some(item) => item.as_u64().map_err(|error| format!("{:?}", error)),
+            None => Err("Missing key derivation parameter".into()),
+        }
+    };
+    let integer_u32 = |index: usize| -> Result<u32, String> {
+        u32::try_from(integer(index)?).map_err(|_| "Key derivation parameter too large".into())
+    };
+    let kdf = match kdf_oid.as_str() {
+        "1.2.840.113549.1.5.12" => {
+            // keyLength is optional, so the prf is the only SEQUENCE left
+            let prf = match kdf_items[2..]
+                .iter()
+                .find(|item| item.as_sequence().is_ok())
+            {
+                Some(prf) => {
+                    let (prf_oid, _) = algorithm_identifier(prf)?;
+                    match prf_name(&prf_oid) {
+                        Some(name) => name.to_string(),
+                        None => prf_oid,
+                    }
+                }
+                None => "hmacWithSHA1".to_string(),
+            };
+            Kdf::Pbkdf2(prf, integer_u32(1)?, salt.len())
+        }
+        "1.3.6.1.4.1.11591.4.11" => Kdf::Scrypt(integer(1)?, integer_u32(2)?, integer_u32(3)?),
+        _ => Kdf::Unknown(kdf_oid),
+    };
+    let (scheme_oid, iv) = algorithm_identifier(&items[1])?;
+    let (cipher_name, decrypt_name) = match encryption_scheme(&scheme_oid) {
+        Some((cipher_name, decrypt_name)) => (cipher_name.to_string(), decrypt_name),
+        None => (scheme_oid, None),
+    };
+    let iv = match iv {
+        Some(iv) => slice(iv)?,
+        None => &[],
+    };
+    Ok(Pbes2 {
+        kdf,
+        salt,
+        cipher_name,
+        decrypt_name,
+        iv,
+    })
+}
+
+fn pbes2_decrypt(
+    pbes2: &Pbes2,
+    passphrase: &[u8],
+    data: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let decrypt_name = match pbes2.decrypt_name {
+        Some(name) => name,
+        None => return Err(format!("Unsupported cipher {}", pbes2.cipher_name)),
+    };
+    let spec = match cipher::spec(decrypt_name) {
+        Some(spec) => spec,
+        None => return Err(format!("Unsupported cipher {}", pbes2.cipher_name)),
+    };
+    if pbes2.iv.len() != spec.iv_length {
+        return Err("Invalid IV length".into());
+    }
+    let mut key = Zeroizing::new(vec![0u8; spec.key_length]);
+    match pbes2.kdf {
+        Kdf::Pbkdf2(ref prf, iterations, _) => {
+            pbkdf2(prf, passphrase, pbes2.salt, iterations, &mut key)?
+        }
+        ref kdf => return Err(format!("Unsupported {}", kdf)),
+    }
+    let mut plaintext = Zeroizing::new(data.to_owned());
+    cipher::decrypt(decrypt_name, &key, pbes2.iv, &mut plaintext, &[])?;
+    cipher::unpad(&mut plaintext, spec.block_size)?;
+    Ok(plaintext)
+}
+
+/// Parse an unencrypted "PRIVATE KEY" block
+pub fn parse(data: &[u8]) -> Result<SshKey, String> {
+    let mut ssh_key: SshKey = Default::default();
+    let (algorithm, blob) = private_key_info(data)?;
+    ssh_key.algorithm = algorithm;
+    ssh_key.public_blob = blob;
+    Ok(ssh_key)
+}
+
+/*
+EncryptedPrivateKeyInfo ::= SEQUENCE {
+  encryptionAlgorithm  EncryptionAlgorithmIdentifier,
+  encryptedData        EncryptedData
+}
+*/
+/// Parse an "ENCRYPTED PRIVATE KEY" block,
+/// decrypting it when a `passphrase` is given
+pub fn parse_encrypted(data: &[u8], passphrase: Option<&[u8]>) -> Result<SshKey, String> {
+    let mut ssh_key = SshKey {
+        is_encrypted: true,
+        ..Default::default()
+    };
+    let info = der(data)?;
+    let items = sequence(&info)?;
+    if items.len() != 2 {
+        return Err("Invalid PKCS#8 EncryptedPrivateKeyInfo".into());
+    }
+    let (scheme, parameters) = algorithm_identifier(&items[0])?;
+    let encrypted = slice(&items[1])?;
+    if scheme != "1.2.840.113549.1.5.13" {
+        ssh_key.kdf = Some(match pbes1_name(&scheme) {
+            Some(name) => Kdf::Pbes1(name.into()),
+            None => Kdf::Unknown(scheme),
+        });
+        if passphrase.is_some() {
+            return Err(format!("Unsupported {}", ssh_key.kdf.unwrap()));
+        }
+        return Ok(ssh_key);
+    }
+    let pbes2 = pbes2_parameters(parameters)?;
+    if let Some(passphrase) = passphrase {
+        let plaintext = pbes2_decrypt(&pbes2, passphrase, encrypted)?;
+        // Garbage DER after decrypting means the padding check got lucky
+        let (algorithm, blob) =
+            private_key_info(&plaintext).map_err(|_| "Incorrect passphrase".to_string())?;
+        ssh_key.algorithm = algorithm;
+        ssh_key.public_blob = blob;
+        ssh_key.is_decrypted = true;
+    }
+    ssh_key.cipher = Some(pbes2.cipher_name);
+    ssh_key.cipher_iv = Some(pbes2.iv.to_owned());
+    ssh_key.kdf = Some(pbes2.kdf);
+    Ok(ssh_key)
+}
@@ -0,0 +1,229 @@
+// PuTTY-User-Key-File-2 and PuTTY-User-Key-File-3 (.ppk) private keys
+use argon2::{Algorithm as Argon2Algorithm, Argon2, Params, Version};
+use base64;
+use cipher;
+use hex;
+use hmac::{Hmac, Mac};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use ssh_key::{peek_algorithm, push_string, Kdf, SshKey};
+use zeroize::Zeroizing;
+
+// Far above PuTTY's defaults (8192 KiB, 13 to 34 passes, parallelism 1),
+// low enough that a crafted key can't hang the scan or exhaust memory
+const MAX_ARGON2_MEMORY: u32 = 1 << 20;
+const MAX_ARGON2_PASSES: u32 = 1000;
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+#[derive(Default)]
+struct Ppk {
+    version: u8,
+    algorithm: String,
+    encryption: String,
+    comment: String,
+    public: Vec<u8>,
+    private: Vec<u8>,
+    // Key-Derivation, Argon2-Memory, Argon2-Passes, Argon2-Parallelism
+    argon2: Option<(String, u32, u32, u32)>,
+    argon2_salt: Vec<u8>,
+    mac: Vec<u8>,
+}
+
+fn hex_decode(value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value).map_err(|_| format!("Invalid hex {}", value))
+}
+
+fn number(tag: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {} {}", tag, value))
+}
+
+fn read(bytes: &[u8]) -> Result<Ppk, String> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+    let mut ppk: Ppk = Default::default();
+    let mut argon2 = (String::new(), 0, 0, 0);
+    while let Some(line) = lines.next() {
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(2, ": ");
+        let tag = parts.next().unwrap_or("");
+        let value = match parts.next() {
+            Some(value) => value,
+            None => return Err(format!("Invalid ppk line {}", line)),
+        };
+        match tag {
+            "PuTTY-User-Key-File-2" | "PuTTY-User-Key-File-3" => {
+                ppk.version = if tag.ends_with('2') { 2 } else { 3 };
+                ppk.algorithm = value.into();
+            }
+            "Encryption" => ppk.encryption = value.into(),
+            "Comment" => ppk.comment = value.into(),
+            "Public-Lines" | "Private-Lines" => {
+                let mut encoded = String::new();
+                for _ in 0..number(tag, value)? {
+                    match lines.next() {
+                        Some(line) => encoded.push_str(line),
+                        None => return Err(format!("Truncated {}", tag)),
+                    }
+                }
+                let decoded = base64::decode(&encoded).map_err(|_| "Invalid Base64".to_string())?;
+                if tag == "Public-Lines" {
+                    ppk.public = decoded;
+                } else {
+                    ppk.private = decoded;
+                }
+            }
+            "Key-Derivation" => argon2.0 = value.into(),
+            "Argon2-Memory" => argon2.1 = number(tag, value)?,
+            "Argon2-Passes" => argon2.2 = number(tag, value)?,
+            "Argon2-Parallelism" => argon2.3 = number(tag, value)?,
+            "Argon2-Salt" => ppk.argon2_salt = hex_decode(value)?,
+            "Private-MAC" => ppk.mac = hex_decode(value)?,
+            // Unknown headers are skipped, as PuTTY does
+            _ => (),
+        }
+    }
+    if ppk.version == 0 {
+        return Err("Missing PuTTY-User-Key-File line".into());
+    }
+    if ppk.public.is_empty() || ppk.mac.is_empty() {
+        return Err("Missing Public-Lines or Private-MAC".into());
+    }
+    if !argon2.0.is_empty() {
+        ppk.argon2 = Some(argon2);
+    }
+    Ok(ppk)
+}
+
+// Everything covered by the Private-MAC, with the private blob decrypted
+fn mac_data(ppk: &Ppk, private: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut data = Zeroizing::new(vec![]);
+    push_string(&mut data, ppk.algorithm.as_bytes());
+    push_string(&mut data, ppk.encryption.as_bytes());
+    push_string(&mut data, ppk.comment.as_bytes());
+    push_string(&mut data, &ppk.public);
+    push_string(&mut data, private);
+    data
+}
+
+// Cipher key, IV and MAC key
+type KeyIvMac = (Zeroizing<Vec<u8>>, Vec<u8>, Zeroizing<Vec<u8>>);
+
+// v2 derives the cipher key from two SHA-1 hashes and uses a zero IV
+fn v2_key_iv_mac(passphrase: &[u8]) -> KeyIvMac {
+    let mut key = Zeroizing::new(vec![]);
+    for counter in &[0u32, 1] {
+        let mut hasher = Sha1::new();
+        hasher.update(counter.to_be_bytes());
+        hasher.update(passphrase);
+        key.extend_from_slice(&hasher.finalize());
+    }
+    key.truncate(32);
+    let mut hasher = Sha1::new();
+    hasher.update(b"putty-private-key-file-mac-key");
+    hasher.update(passphrase);
+    let mac_key = Zeroizing::new(hasher.finalize().to_vec());
+    (key, vec![0; 16], mac_key)
+}
+
+// v3 derives cipher key, IV and MAC key in one Argon2 run
+fn v3_key_iv_mac(ppk: &Ppk, passphrase: &[u8]) -> Result<KeyIvMac, String> {
+    let (ref variant, memory, passes, parallelism) = match ppk.argon2 {
+        Some(ref argon2) => argon2.clone(),
+        None => return Err("Missing Key-Derivation".into()),
+    };
+    let algorithm = match variant.as_str() {
+        "Argon2d" => Argon2Algorithm::Argon2d,
+        "Argon2i" => Argon2Algorithm::Argon2i,
+        "Argon2id" => Argon2Algorithm::Argon2id,
+        _ => return Err(format!("Unsupported kdf {}", variant)),
+    };
+    if memory > MAX_ARGON2_MEMORY
+        || passes > MAX_ARGON2_PASSES
+        || parallelism > MAX_ARGON2_PARALLELISM
+    {
+        return Err(format!(
+            "Argon2 parameters too large: {} KiB, {} passes, parallelism {}",
+            memory, passes, parallelism
+        ));
+    }
+    let params = Params::new(memory, passes, parallelism, Some(80))
+        .map_err(|error| format!("Invalid Argon2 parameters: {}", error))?;
+    let mut output = Zeroizing::new(vec![0u8; 80]);
+    Argon2::new(algorithm, Version::V0x13, params)
+        .hash_password_into(passphrase, &ppk.argon2_salt, &mut output)
+        .map_err(|error| format!("Argon2 error: {}", error))?;
+    Ok((
+        Zeroizing::new(output[..32].to_vec()),
+        output[32..48].to_vec(),
+        Zeroizing::new(output[48..].to_vec()),
+    ))
+}
+
+fn check_mac(ppk: &Ppk, mac_key: &[u8], private: &[u8]) -> bool {
+    let data = mac_data(ppk, private);
+    if ppk.version == 2 {
+        let mut mac = Hmac::<Sha1>::new_from_slice(mac_key).expect("HMAC takes any key length");
+        mac.update(&data);
+        mac.verify_slice(&ppk.mac).is_ok()
+    } else {
+        let mut mac = Hmac::<Sha256>::new_from_slice(mac_key).expect("HMAC takes any key length");
+        mac.update(&data);
+        mac.verify_slice(&ppk.mac).is_ok()
+    }
+}
+
+/// Parse a PuTTY .ppk file. The Private-MAC is checked for unencrypted keys,
+/// and for encrypted keys when a `passphrase` is given.
+pub fn parse(bytes: &[u8], passphrase: Option<&[u8]>) -> Result<SshKey, String> {
+    let ppk = read(bytes)?;
+    let is_encrypted = ppk.encryption != "none";
+    let mut ssh_key = SshKey {
+        // The public key is in the clear, so even encrypted keys can be paired
+        algorithm: peek_algorithm(false, &ppk.public)?,
+        comment: Some(ppk.comment.clone()),
+        is_encrypted,
+        public_blob: ppk.public.clone(),
+        format: Some(format!("PuTTY PPK v{}", ppk.version)),
+        ..Default::default()
+    };
+    if !is_encrypted {
+        // Unencrypted v2 keys still MAC with the empty passphrase
+        let mac_key = if ppk.version == 2 {
+            v2_key_iv_mac(b"").2
+        } else {
+            Zeroizing::new(vec![])
+        };
+        if !check_mac(&ppk, &mac_key, &ppk.private) {
+            return Err("Private-MAC mismatch, the key file is corrupt".into());
+        }
+        return Ok(ssh_key);
+    }
+    if ppk.encryption != "aes256-cbc" {
+        return Err(format!("Unsupported cipher {}", ppk.encryption));
+    }
+    ssh_key.cipher = Some(ppk.encryption.clone());
+    ssh_key.kdf = Some(match ppk.argon2 {
+        Some((ref variant, memory, passes, parallelism)) => {
+            Kdf::Argon2(variant.clone(), memory, passes, parallelism)
+        }
+        None => Kdf::PuttySha1,
+    });
+    if let Some(passphrase) = passphrase {
+        let (key, iv, mac_key) = if ppk.version == 2 {
+            v2_key_iv_mac(passphrase)
+        } else {
+            v3_key_iv_mac(&ppk, passphrase)?
+        };
+        let mut private = Zeroizing::new(ppk.private.clone());
+        cipher::decrypt(&ppk.encryption, &key, &iv, &mut private, &[])?;
+        if !check_mac(&ppk, &mac_key, &private) {
+            return Err("Incorrect passphrase".into());
+        }
+        ssh_key.is_decrypted = true;
+    }
+    Ok(ssh_key)
+}
@@ -1,13 +1,19 @@
-use der_parser::oid::Oid;
+use asn1::{children, der, elements, oid};
+use bcrypt_pbkdf;
+use cipher;
 use der_parser::{
-    der_read_element_content_as, parse_der_implicit, parse_der_integer, parse_der_octetstring,
-    DerObject, DerObjectContent, DerTag, DER_OBJ_TOOSHORT,
+    der_read_element_content_as, parse_der_bitstring, parse_der_explicit, parse_der_implicit,
+    parse_der_integer, parse_der_octetstring, DerObject, DerObjectContent, DerTag,
+    DER_OBJ_TOOSHORT,
 };
 use nom;
-use nom::IResult;
+use nom::{be_u8, IResult};
 use nom_pem;
-use nom_pem::{HeaderEntry, ProcTypeType};
-use ssh_key::{peek_algorithm, Algorithm, SshKey};
+use nom_pem::{HeaderEntry, ProcTypeType, RFC1423Algorithm};
+use oids::ssh_curve;
+use pkcs8;
+use ssh_key::{parse_error, peek_algorithm, push_string, Algorithm, Kdf, SshKey};
+use zeroize::Zeroizing;
 
 // My code does not directly use these names. Why do I need to `use` them?
 use nom::be_u32;
@@ -19,8 +25,62 @@ fn is_encrypted(headers: &[HeaderEntry]) -> bool {
     })
 }
 
+// DEK-Info: the cipher (by its PEM and, if supported, OpenSSH names) and IV
+fn dek_info(headers: &[HeaderEntry]) -> Option<(&'static str, Option<&'static str>, Vec<u8>)> {
+    headers.iter().find_map(|header| match *header {
+        HeaderEntry::DEKInfo(ref algorithm, ref iv) => {
+            let (pem_name, openssh_name) = match *algorithm {
+                RFC1423Algorithm::DES_CBC => ("DES-CBC", None),
+                RFC1423Algorithm::DES_EDE3_CBC => ("DES-EDE3-CBC", Some("3des-cbc")),
+                RFC1423Algorithm::AES_128_CBC => ("AES-128-CBC", Some("aes128-cbc")),
+                RFC1423Algorithm::AES_192_CBC => ("AES-192-CBC", Some("aes192-cbc")),
+                RFC1423Algorithm::AES_256_CBC => ("AES-256-CBC", Some("aes256-cbc")),
+            };
+            Some((pem_name, openssh_name, iv.clone()))
+        }
+        _ => None,
+    })
+}
+
+// The first 8 bytes of the IV double as the salt
+fn pem_decrypt(
+    cipher_name: &str,
+    iv: &[u8],
+    passphrase: &[u8],
+    data: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let spec = match cipher::spec(cipher_name) {
+        Some(spec) => spec,
+        None => return Err(format!("Unsupported cipher {}", cipher_name)),
+    };
+    if iv.len() != spec.iv_length {
+        return Err("Invalid IV length".into());
+    }
+    let key = cipher::evp_bytes_to_key(passphrase, &iv[..8], spec.key_length);
+    let mut plaintext = Zeroizing::new(data.to_owned());
+    cipher::decrypt(cipher_name, &key, iv, &mut plaintext, &[])?;
+    cipher::unpad(&mut plaintext, spec.block_size)?;
+    Ok(plaintext)
+}
+
+// The bytes of a parsed DER object, as an error rather than a panic
+fn der_bytes<'a>(object: &DerObject<'a>) -> Result<&'a [u8], String> {
+    object
+        .content
+        .as_slice()
+        .map_err(|error| format!("{:?}", error))
+}
+
+// A positive INTEGER without its leading null byte
+fn unsigned(integer: &[u8]) -> Result<&[u8], String> {
+    match integer.split_first() {
+        Some((_, rest)) if !rest.is_empty() => Ok(rest),
+        _ => Err("Empty DER integer".into()),
+    }
+}
+
 // https://superuser.com/a/820638/34245
-fn dsa_private(input: &[u8]) -> Result<Algorithm, String> {
+fn dsa_private(input: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
     match parse_der_sequence_defined!(
         input,
         parse_der_integer, // version
@@ -30,13 +90,21 @@ fn dsa_private(input: &[u8]) -> Result<Algorithm, String> {
         parse_der_integer, // public_key
         parse_der_integer, // private_key
     ) {
-        Ok((_unparsed_suffix, der)) => {
-            assert_eq!(_unparsed_suffix.len(), 0);
-            let der_objects = der.as_sequence().unwrap();
-            let p_integer = der_objects[1].content.as_slice().unwrap();
+        Ok((unparsed_suffix, der)) => {
+            if !unparsed_suffix.is_empty() {
+                return Err("Trailing data after the DSA private key".into());
+            }
+            let der_objects = der.as_sequence().map_err(|error| format!("{:?}", error))?;
+            let p_integer = der_bytes(&der_objects[1])?;
+            // ssh-dss public blob is p, q, g, y
+            let mut blob = vec![];
+            push_string(&mut blob, b"ssh-dss");
+            for der_object in &der_objects[1..5] {
+                push_string(&mut blob, der_bytes(der_object)?);
+            }
             // strip leading null byte
-            let p_integer = &p_integer[1..];
-            Ok(Algorithm::Dsa(p_integer.to_owned()))
+            let p_integer = unsigned(p_integer)?;
+            Ok((Algorithm::Dsa(p_integer.to_owned()), blob))
         }
         Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => Err(format!("{:?}", error)),
         Err(nom::Err::Incomplete(needed)) => Err(format!("Incomplete parse: {:?}", needed)),
@@ -61,7 +129,7 @@ fn dsa_private(input: &[u8]) -> Result<Algorithm, String> {
           otherPrimeInfos   OtherPrimeInfos OPTIONAL
       }
 */
-fn rsa_private(input: &[u8]) -> Result<Algorithm, String> {
+pub fn rsa_private(input: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
     match parse_der_sequence_defined!(
         input,
         parse_der_integer,
@@ -74,19 +142,27 @@ fn rsa_private(input: &[u8]) -> Result<Algorithm, String> {
         parse_der_integer,
         parse_der_integer,
     ) {
-        Ok((_unparsed_suffix, der)) => {
-            assert_eq!(_unparsed_suffix.len(), 0);
-            let der_objects = der.as_sequence().unwrap();
+        Ok((unparsed_suffix, der)) => {
+            if !unparsed_suffix.is_empty() {
+                return Err("Trailing data after the RSA private key".into());
+            }
+            let der_objects = der.as_sequence().map_err(|error| format!("{:?}", error))?;
             // modulus (n) is at index 1
-            // I believe this indexing and unwrapping is safe because
-            // if it parsed correctly, the data should be there
-            let modulus = der_objects[1].content.as_slice().unwrap();
+            // This indexing is safe because the sequence parsed
+            // with all nine integers
+            let modulus = der_bytes(&der_objects[1])?;
+            let exponent = der_bytes(&der_objects[2])?;
+            // ssh-rsa public blob is e then n
+            let mut blob = vec![];
+            push_string(&mut blob, b"ssh-rsa");
+            push_string(&mut blob, exponent);
+            push_string(&mut blob, modulus);
             // Skip leading null byte.
             // Also we copy the modulus to an owned Vec<u8> here because
             // we want to allow the secret parts of the private key to be freed.
             // We want the secrets in memory as briefly as possible.
-            let modulus = modulus[1..].to_owned();
-            Ok(Algorithm::Rsa(modulus))
+            let modulus = unsigned(modulus)?.to_owned();
+            Ok((Algorithm::Rsa(modulus), blob))
         }
         Err(nom::Err::Error(error)) => Err(format!("{:?}", error)),
         Err(nom::Err::Failure(error)) => Err(format!("{:?}", error)),
@@ -98,16 +174,26 @@ fn der_read_oid_content(i: &[u8], _tag: u8, len: usize) -> IResult<&[u8], DerObj
     der_read_element_content_as(i, DerTag::Oid as u8, len)
 }
 
-fn der_read_bitstring_content(i: &[u8], _tag: u8, len: usize) -> IResult<&[u8], DerObjectContent> {
-    der_read_element_content_as(i, DerTag::BitString as u8, len)
-}
-
 fn parse_oid(i: &[u8]) -> IResult<&[u8], DerObject> {
     parse_der_implicit(i, 0, der_read_oid_content)
 }
 
 fn parse_bitstring(i: &[u8]) -> IResult<&[u8], DerObject> {
-    parse_der_implicit(i, 1, der_read_bitstring_content)
+    parse_der_explicit(i, 1, parse_der_bitstring)
+}
+
+// parse_oid hands back the [0] parameters with their tag and length
+// still in the OID, so read the dotted curve OID from the DER again
+fn curve_oid(input: &[u8]) -> Result<String, String> {
+    let key = elements(input)?;
+    let items = match key.first() {
+        Some(sequence) => children(sequence)?,
+        None => return Err("Empty EC private key".into()),
+    };
+    match items.iter().find(|item| item.tag == 0xa0) {
+        Some(parameters) => oid(&der(parameters.contents)?),
+        None => Err("EC key without a named curve".into()),
+    }
 }
 
 // http://www.secg.org/sec1-v2.pdf
@@ -127,7 +213,7 @@ ecies [3] ECIES-Ciphertext-Value, sharedinfo [4] ASN1SharedInfo, ...
    51:d=1  hl=2 l=  68 cons: cont [ 1 ]
    53:d=2  hl=2 l=  66 prim: BIT STRING
 */
-fn ecdsa_private(input: &[u8]) -> Result<Algorithm, String> {
+fn ecdsa_private(input: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
     match parse_der_sequence_defined!(
         input,
         parse_der_integer,
@@ -135,26 +221,29 @@ fn ecdsa_private(input: &[u8]) -> Result<Algorithm, String> {
         parse_oid,
         parse_bitstring,
     ) {
-        Ok((_unparsed_suffix, der)) => {
-            assert_eq!(_unparsed_suffix.len(), 0);
-            let seq = der.as_sequence().unwrap();
-            let oid = seq[2].content.as_context_specific().unwrap();
-            let oid = oid.1.unwrap();
-            let oid = oid.content.as_oid().unwrap();
-            let point = seq[3].content.as_context_specific().unwrap();
-            let point = point.1.unwrap().content.as_slice().unwrap();
-            // strip off 2 bytes of the ASN wrapper
-            let point = (&point[2..]).to_owned();
-            if oid == &Oid::from(&[0u64, 6, 8, 42, 840, 10_045, 3, 1, 7]) {
-                return Ok(Algorithm::Ecdsa("nistp256".into(), point));
+        Ok((unparsed_suffix, der)) => {
+            if !unparsed_suffix.is_empty() {
+                return Err("Trailing data after the EC private key".into());
             }
-            if oid == &Oid::from(&[0u64, 6, 5, 43, 132, 0, 34]) {
-                return Ok(Algorithm::Ecdsa("nistp384".into(), point));
-            }
-            if oid == &Oid::from(&[0u64, 6, 5, 43, 132, 0, 35]) {
-                return Ok(Algorithm::Ecdsa("nistp521".into(), point));
-            }
-            Ok(Algorithm::Unknown)
+            let seq = der.as_sequence().map_err(|error| format!("{:?}", error))?;
+            let point = seq[3]
+                .content
+                .as_context_specific()
+                .map_err(|error| format!("{:?}", error))?;
+            // BIT STRING content with the unused-bits byte already removed
+            let point = match point.1 {
+                Some(point) => der_bytes(&point)?.to_owned(),
+                None => return Err("EC private key without a public point".into()),
+            };
+            let curve = match ssh_curve(&curve_oid(input)?) {
+                Some(curve) => curve,
+                None => return Ok((Algorithm::Unknown, vec![])),
+            };
+            let mut blob = vec![];
+            push_string(&mut blob, format!("ecdsa-sha2-{}", curve).as_bytes());
+            push_string(&mut blob, curve.as_bytes());
+            push_string(&mut blob, &point);
+            Ok((Algorithm::Ecdsa(curve, point), blob))
         }
         Err(nom::Err::Failure(error)) => Err(format!("{:?}", error)),
         Err(nom::Err::Error(error)) => Err(format!("{:?}", error)),
@@ -170,81 +259,379 @@ string	kdfoptions
 int	number of keys N
 string	publickey1
 string	publickey2
+...
+string	encrypted, padded list of private keys
 */
 named!(
-    nom_openssh_key_v1_private<(&[u8], &[u8])>,
+    nom_openssh_key_v1_private<(&[u8], &[u8], &[u8], Vec<&[u8]>, &[u8])>,
     do_parse!(
         tag!(b"openssh-key-v1\0")
             >> cipher_name: length_bytes!(be_u32)
-            >> _kdf_name: length_bytes!(be_u32)
-            >> _kdf_options: length_bytes!(be_u32)
-            >> _key_count: tag!(&[0, 0, 0, 1])
-            >> key: length_bytes!(be_u32)
-            >> (cipher_name, key)
+            >> kdf_name: length_bytes!(be_u32)
+            >> kdf_options: length_bytes!(be_u32)
+            >> key_count: be_u32
+            >> keys: count!(length_bytes!(be_u32), key_count as usize)
+            >> private: length_bytes!(be_u32)
+            >> (cipher_name, kdf_name, kdf_options, keys, private)
     )
 );
 
-fn openssh_key_v1_private(bytes: &[u8]) -> Result<SshKey, String> {
+/*
+string	salt
+uint32	rounds
+*/
+named!(
+    nom_bcrypt_options<(&[u8], u32)>,
+    do_parse!(salt: length_bytes!(be_u32) >> rounds: be_u32 >> (salt, rounds))
+);
+
+fn openssh_kdf(kdf_name: &[u8], kdf_options: &[u8]) -> Result<Kdf, String> {
+    match kdf_name {
+        b"bcrypt" => {
+            let (_tail, (salt, rounds)) = nom_bcrypt_options(kdf_options).map_err(parse_error)?;
+            Ok(Kdf::Bcrypt(salt.len(), rounds))
+        }
+        _ => Ok(Kdf::Unknown(String::from_utf8_lossy(kdf_name).into())),
+    }
+}
+
+named!(nom_string<&[u8]>, length_bytes!(be_u32));
+
+named!(
+    nom_check_ints<(u32, u32)>,
+    do_parse!(check_int_1: be_u32 >> check_int_2: be_u32 >> (check_int_1, check_int_2))
+);
+
+// The private part of a security key is just a stub referring to the token
+// https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL.u2f
+named!(
+    nom_sk_stub<(u8, &[u8])>,
+    do_parse!(
+        flags: be_u8
+            >> key_handle: length_bytes!(be_u32)
+            >> _reserved: length_bytes!(be_u32)
+            >> (flags, key_handle)
+    )
+);
+
+// Layout of the fields following the key type in the private section
+// (see sshkey_private_serialize in OpenSSH): how many strings/mpints there are,
+// which of them make up the public key blob (in blob order),
+// and whether a security key stub follows them
+fn private_layout(key_type: &[u8]) -> Option<(usize, &'static [usize], bool)> {
+    match key_type {
+        b"ssh-ed25519" => Some((2, &[0], false)),
+        b"ssh-rsa" => Some((6, &[1, 0], false)),
+        b"ssh-dss" => Some((5, &[0, 1, 2, 3], false)),
+        b"ecdsa-sha2-nistp256" | b"ecdsa-sha2-nistp384" | b"ecdsa-sha2-nistp521" => {
+            Some((3, &[0, 1], false))
+        }
+        b"sk-ssh-ed25519@openssh.com" => Some((2, &[0, 1], true)),
+        b"sk-ecdsa-sha2-nistp256@openssh.com" => Some((3, &[0, 1, 2], true)),
+        _ => None,
+    }
+}
+
+/// One key out of the private section of an openssh-key-v1 file
+struct PrivateKey<'a> {
+    // Public key blob rebuilt from the private fields
+    public_blob: Vec<u8>,
+    // Ed25519 private keys embed a second copy of the public point
+    is_consistent: bool,
+    security_key: Option<(u8, &'a [u8])>,
+    comment: &'a [u8],
+}
+
+/*
+string	key type
+...	key type specific fields
+string	comment
+*/
+fn read_private_key<'a>(input: &'a [u8]) -> Result<(&'a [u8], PrivateKey<'a>), String> {
+    let (input, key_type) = nom_string(input).map_err(parse_error)?;
+    let (field_count, public_fields, has_stub) = match private_layout(key_type) {
+        Some(layout) => layout,
+        None => {
+            return Err(format!(
+                "Unsupported private key type {}",
+                String::from_utf8_lossy(key_type)
+            ))
+        }
+    };
+    let (mut input, fields) =
+        count!(input, length_bytes!(be_u32), field_count).map_err(parse_error)?;
+    let mut security_key = None;
+    if has_stub {
+        let (tail, stub) = nom_sk_stub(input).map_err(parse_error)?;
+        input = tail;
+        security_key = Some(stub);
+    }
+    let (input, comment) = nom_string(input).map_err(parse_error)?;
+
+    let mut public_blob = vec![];
+    push_string(&mut public_blob, key_type);
+    for &index in public_fields {
+        push_string(&mut public_blob, fields[index]);
+    }
+    let is_consistent = match key_type {
+        b"ssh-ed25519" => fields[1].len() == 64 && &fields[1][32..] == fields[0],
+        _ => true,
+    };
+    Ok((
+        input,
+        PrivateKey {
+            public_blob,
+            is_consistent,
+            security_key,
+            comment,
+        },
+    ))
+}
+
+// Padding is the bytes 1, 2, 3... up to the cipher block size
+fn is_valid_padding(padding: &[u8], section_length: usize, block_size: usize) -> bool {
+    section_length.is_multiple_of(block_size)
+        && padding.len() < block_size
+        && padding
+            .iter()
+            .enumerate()
+            .all(|(index, &byte)| byte as usize == index + 1)
+}
+
+/*
+uint32	checkint
+uint32	checkint
+...	private key 1 (see read_private_key)
+...	private key 2
+...
+byte[]	padding
+*/
+fn read_private_section(
+    ssh_keys: &mut [SshKey],
+    private: &[u8],
+    block_size: usize,
+) -> Result<(), String> {
+    let (mut input, (check_int_1, check_int_2)) = nom_check_ints(private).map_err(parse_error)?;
+    if check_int_1 != check_int_2 {
+        // Nothing after this can be trusted
+        for ssh_key in ssh_keys.iter_mut() {
+            ssh_key
+                .errors
+                .push("check integers differ, private key is corrupt".into());
+        }
+        return Ok(());
+    }
+    for ssh_key in ssh_keys.iter_mut() {
+        let (tail, private_key) = read_private_key(input)?;
+        input = tail;
+        if private_key.public_blob != ssh_key.public_blob || !private_key.is_consistent {
+            ssh_key
+                .errors
+                .push("private key does not match its public key".into());
+        }
+        if !private_key.comment.is_empty() {
+            ssh_key.comment = Some(String::from_utf8_lossy(private_key.comment).into());
+        }
+        if let Some((flags, key_handle)) = private_key.security_key {
+            match ssh_key.algorithm {
+                Algorithm::SkEd25519(_, ref mut security_key)
+                | Algorithm::SkEcdsa(_, _, ref mut security_key) => {
+                    security_key.flags = Some(flags);
+                    security_key.key_handle_length = Some(key_handle.len());
+                }
+                _ => (),
+            }
+        }
+    }
+    if !is_valid_padding(input, private.len(), block_size) {
+        for ssh_key in ssh_keys.iter_mut() {
+            ssh_key
+                .errors
+                .push("invalid padding after the private key".into());
+        }
+    }
+    Ok(())
+}
+
+// bcrypt_pbkdf turns the passphrase into the cipher key and IV
+fn openssh_decrypt(
+    cipher_name: &str,
+    kdf_name: &[u8],
+    kdf_options: &[u8],
+    passphrase: &[u8],
+    private: &[u8],
+    tag: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let spec = match cipher::spec(cipher_name) {
+        Some(spec) => spec,
+        None => return Err(format!("Unsupported cipher {}", cipher_name)),
+    };
+    if kdf_name != b"bcrypt" {
+        return Err(format!(
+            "Unsupported kdf {}",
+            String::from_utf8_lossy(kdf_name)
+        ));
+    }
+    if tag.len() < spec.tag_length {
+        return Err("Missing authentication tag".into());
+    }
+    let (_tail, (salt, rounds)) = nom_bcrypt_options(kdf_options).map_err(parse_error)?;
+    let mut key_and_iv = Zeroizing::new(vec![0u8; spec.key_length + spec.iv_length]);
+    bcrypt_pbkdf::bcrypt_pbkdf(passphrase, salt, rounds, &mut key_and_iv)
+        .map_err(|error| format!("bcrypt_pbkdf error: {:?}", error))?;
+    let (key, iv) = key_and_iv.split_at(spec.key_length);
+    let mut plaintext = Zeroizing::new(private.to_owned());
+    cipher::decrypt(
+        cipher_name,
+        key,
+        iv,
+        &mut plaintext,
+        &tag[..spec.tag_length],
+    )?;
+    Ok(plaintext)
+}
+
+fn openssh_key_v1_private(bytes: &[u8], passphrase: Option<&[u8]>) -> Result<Vec<SshKey>, String> {
     match nom_openssh_key_v1_private(bytes) {
-        Ok((_tail, (cipher_name, key_bytes))) => {
-            let mut ssh_key: SshKey = Default::default();
-            ssh_key.is_encrypted = cipher_name != b"none";
-            match peek_algorithm(ssh_key.is_encrypted, key_bytes) {
-                Ok(algorithm) => {
-                    ssh_key.algorithm = algorithm;
-                    Ok(ssh_key)
+        Ok((tail, (cipher_name, kdf_name, kdf_options, keys, private))) => {
+            let is_encrypted = cipher_name != b"none";
+            let mut ssh_keys = vec![];
+            for key_bytes in keys {
+                let (cipher, kdf) = if is_encrypted {
+                    (
+                        Some(String::from_utf8_lossy(cipher_name).into()),
+                        Some(openssh_kdf(kdf_name, kdf_options)?),
+                    )
+                } else {
+                    (None, None)
+                };
+                ssh_keys.push(SshKey {
+                    algorithm: peek_algorithm(is_encrypted, key_bytes)?,
+                    is_encrypted,
+                    // The public key is stored in the clear even when encrypted
+                    public_blob: key_bytes.to_owned(),
+                    cipher,
+                    kdf,
+                    ..Default::default()
+                });
+            }
+            if ssh_keys.is_empty() {
+                return Err("No keys".into());
+            }
+            if !is_encrypted {
+                // Unencrypted keys are padded to the "none" cipher's 8 byte blocks
+                read_private_section(&mut ssh_keys, private, 8)?;
+            } else if let Some(passphrase) = passphrase {
+                let cipher_name = String::from_utf8_lossy(cipher_name);
+                // AEAD ciphers put their tag right after the private section
+                let plaintext = openssh_decrypt(
+                    &cipher_name,
+                    kdf_name,
+                    kdf_options,
+                    passphrase,
+                    private,
+                    tail,
+                )?;
+                let (_tail, (check_int_1, check_int_2)) =
+                    nom_check_ints(&plaintext).map_err(parse_error)?;
+                if check_int_1 != check_int_2 {
+                    return Err("Incorrect passphrase".into());
                 }
-                Err(message) => Err(message),
+                for ssh_key in &mut ssh_keys {
+                    ssh_key.algorithm = peek_algorithm(false, &ssh_key.public_blob)?;
+                    ssh_key.is_decrypted = true;
+                }
+                let block_size = cipher::spec(&cipher_name).map_or(8, |spec| spec.block_size);
+                read_private_section(&mut ssh_keys, &plaintext, block_size)?;
             }
+            Ok(ssh_keys)
         }
         Err(nom::Err::Error(_error)) | Err(nom::Err::Failure(_error)) => Err("Parse error".into()),
         Err(nom::Err::Incomplete(_needed)) => Err("Didn't fully parse".into()),
     }
 }
 
-pub fn parse(bytes: &[u8]) -> Result<SshKey, String> {
+/// Parse a PEM private key file. Most formats hold one key,
+/// but openssh-key-v1 files can hold several.
+/// Encrypted keys are decrypted when a `passphrase` is given.
+pub fn parse(bytes: &[u8], passphrase: Option<&[u8]>) -> Result<Vec<SshKey>, String> {
     match nom_pem::decode_block(bytes) {
         Ok(block) => {
             let mut ssh_key: SshKey = Default::default();
             ssh_key.is_encrypted = is_encrypted(&block.headers);
             ssh_key.algorithm = match block.block_type {
                 "DSA PRIVATE KEY" => Algorithm::Dsa(vec![]),
-                "EC PRIVATE KEY" => Algorithm::Ecdsa("unkown".into(), vec![]),
+                "EC PRIVATE KEY" => Algorithm::Ecdsa(String::new(), vec![]),
                 "RSA PRIVATE KEY" => Algorithm::Rsa(vec![]),
                 _ => Algorithm::Unknown,
             };
+            let dek_info = dek_info(&block.headers);
+            if let Some((pem_name, _, ref iv)) = dek_info {
+                ssh_key.cipher = Some(pem_name.into());
+                ssh_key.cipher_iv = Some(iv.clone());
+                ssh_key.kdf = Some(Kdf::OpensslMd5);
+            }
+            let mut plaintext = None;
             if ssh_key.is_encrypted {
-                // Can't determine details without passphrase
-                return Ok(ssh_key);
+                match (passphrase, dek_info) {
+                    (Some(passphrase), Some((pem_name, openssh_name, iv))) => {
+                        let cipher_name = match openssh_name {
+                            Some(name) => name,
+                            None => return Err(format!("Unsupported cipher {}", pem_name)),
+                        };
+                        plaintext = Some(pem_decrypt(cipher_name, &iv, passphrase, &block.data)?);
+                        ssh_key.is_decrypted = true;
+                    }
+                    // Can't determine details without passphrase
+                    _ => return Ok(vec![ssh_key]),
+                }
             }
+            let data: &[u8] = match plaintext {
+                Some(ref plaintext) => plaintext,
+                None => &block.data,
+            };
+            // Garbage DER after decrypting means the padding check got lucky
+            let is_decrypted = ssh_key.is_decrypted;
+            let der_error = |error: String| {
+                if is_decrypted {
+                    "Incorrect passphrase".into()
+                } else {
+                    error
+                }
+            };
             match block.block_type {
                 "DSA PRIVATE KEY" => {
-                    ssh_key.algorithm = dsa_private(&block.data)?;
+                    let (algorithm, blob) = dsa_private(data).map_err(der_error)?;
+                    ssh_key.algorithm = algorithm;
+                    ssh_key.public_blob = blob;
                 }
                 "RSA PRIVATE KEY" => {
-                    ssh_key.algorithm = rsa_private(&block.data)?;
+                    let (algorithm, blob) = rsa_private(data).map_err(der_error)?;
+                    ssh_key.algorithm = algorithm;
+                    ssh_key.public_blob = blob;
                 }
                 "EC PRIVATE KEY" => {
-                    ssh_key.algorithm = ecdsa_private(&block.data)?;
+                    let (algorithm, blob) = ecdsa_private(data).map_err(der_error)?;
+                    ssh_key.algorithm = algorithm;
+                    ssh_key.public_blob = blob;
                 }
                 "OPENSSH PRIVATE KEY" => {
                     if block.data.starts_with(b"openssh-key-v1\0") {
-                        match openssh_key_v1_private(&block.data) {
-                            Ok(key) => {
-                                ssh_key = key;
-                            }
-                            Err(error) => {
-                                return Err(format!("openssh-key-v1 error: {:?}", error));
-                            }
-                        }
+                        return match openssh_key_v1_private(&block.data, passphrase) {
+                            Ok(keys) => Ok(keys),
+                            Err(error) => Err(format!("openssh-key-v1 error: {:?}", error)),
+                        };
                     }
                 }
+                "PRIVATE KEY" => {
+                    return Ok(vec![pkcs8::parse(&block.data)?]);
+                }
                 "ENCRYPTED PRIVATE KEY" => {
-                    ssh_key.is_encrypted = true;
+                    return Ok(vec![pkcs8::parse_encrypted(&block.data, passphrase)?]);
                 }
                 _ => (),
             };
-            Ok(ssh_key)
+            Ok(vec![ssh_key])
         }
         Err(error) => Err(format!("PEM error: {:?}", error)),
     }
@@ -1,6 +1,9 @@
+use asn1::{algorithm_identifier, der, oid, sequence, slice};
 use base64;
-use ssh_key::{peek_algorithm, SshKey};
 use nom;
+use nom_pem;
+use oids::ssh_curve;
+use ssh_key::{peek_algorithm, push_string, Algorithm, SshKey};
 
 named!(space_sep, is_a_s!(" \t"));
 named!(value, is_not_s!(" \t"));
@@ -16,24 +19,220 @@ named!(
     )
 );
 
-pub fn parse(bytes: &[u8]) -> Result<SshKey, String> {
+/// Split a one-line OpenSSH public key (or certificate) into
+/// its base64-decoded blob and its comment
+pub fn decode(bytes: &[u8]) -> Result<(Vec<u8>, String), String> {
     match nom_public_key(bytes) {
-        Ok((_remaining, (_label, payload, comment))) => {
-            let mut ssh_key: SshKey = Default::default();
-            ssh_key.is_public = true;
-            ssh_key.comment = Some(String::from_utf8_lossy(comment).into_owned());
-            match base64::decode(payload) {
-                Ok(key_bytes) => match peek_algorithm(false, &key_bytes) {
-                    Ok(algorithm) => {
-                        ssh_key.algorithm = algorithm;
-                        Ok(ssh_key)
-                    }
-                    Err(message) => Err(message),
-                },
-                Err(_) => Err("Invalid Base64".into()),
-            }
-        }
+        Ok((_remaining, (_label, payload, comment))) => match base64::decode(payload) {
+            Ok(key_bytes) => Ok((key_bytes, String::from_utf8_lossy(comment).into_owned())),
+            Err(_) => Err("Invalid Base64".into()),
+        },
         Err(nom::Err::Error(_e)) | Err(nom::Err::Failure(_e)) => Err("Parse error".into()),
         Err(nom::Err::Incomplete(_needed)) => Err("Didn't fully parse".into()),
     }
 }
+
+pub fn parse(bytes: &[u8]) -> Result<SshKey, String> {
+    let (key_bytes, comment) = decode(bytes)?;
+    Ok(SshKey {
+        algorithm: peek_algorithm(false, &key_bytes)?,
+        comment: Some(comment),
+        is_public: true,
+        public_blob: key_bytes,
+        ..Default::default()
+    })
+}
+
+/// Decode the RFC 4716 armor around `label` ("SSH2 PUBLIC KEY" or the ssh.com
+/// "SSH2 ENCRYPTED PRIVATE KEY") into its blob and Comment header.
+/// Header values may be folded with a trailing backslash.
+pub fn decode_rfc4716(bytes: &[u8], label: &str) -> Result<(Vec<u8>, Option<String>), String> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+    if lines.next() != Some(&format!("---- BEGIN {} ----", label)) {
+        return Err(format!("Missing {} begin line", label));
+    }
+    let end = format!("---- END {} ----", label);
+    let mut comment = None;
+    let mut payload = String::new();
+    let mut in_headers = true;
+    let mut found_end = false;
+    while let Some(line) = lines.next() {
+        if line == end {
+            found_end = true;
+            break;
+        }
+        if in_headers && line.contains(':') {
+            let mut header = line.to_owned();
+            while header.ends_with('\\') {
+                header.pop();
+                match lines.next() {
+                    Some(continuation) => header.push_str(continuation),
+                    None => return Err("Unterminated SSH2 header".into()),
+                }
+            }
+            let mut parts = header.splitn(2, ':');
+            let tag = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            if tag.eq_ignore_ascii_case("comment") {
+                let value = if value.len() > 1 && value.starts_with('"') && value.ends_with('"') {
+                    &value[1..value.len() - 1]
+                } else {
+                    value
+                };
+                comment = Some(value.to_owned());
+            }
+            continue;
+        }
+        in_headers = false;
+        payload.push_str(line.trim());
+    }
+    if !found_end {
+        return Err(format!("Missing {} end line", label));
+    }
+    let blob = base64::decode(&payload).map_err(|_| "Invalid Base64".to_string())?;
+    Ok((blob, comment))
+}
+
+/// Parse an RFC 4716 "---- BEGIN SSH2 PUBLIC KEY ----" file,
+/// keeping its Comment header as the key comment
+pub fn parse_rfc4716(bytes: &[u8]) -> Result<SshKey, String> {
+    let (key_bytes, comment) = decode_rfc4716(bytes, "SSH2 PUBLIC KEY")?;
+    Ok(SshKey {
+        algorithm: peek_algorithm(false, &key_bytes)?,
+        comment,
+        is_public: true,
+        public_blob: key_bytes,
+        format: Some("RFC 4716".into()),
+        ..Default::default()
+    })
+}
+
+// Strip the leading null byte DER adds to keep big integers positive
+fn unsigned(integer: &[u8]) -> &[u8] {
+    if integer.len() > 1 && integer[0] == 0 {
+        &integer[1..]
+    } else {
+        integer
+    }
+}
+
+/// Algorithm and ssh public key blob from RSA n and e (DER integer contents)
+pub fn rsa_public(modulus: &[u8], exponent: &[u8]) -> (Algorithm, Vec<u8>) {
+    let mut blob = vec![];
+    push_string(&mut blob, b"ssh-rsa");
+    push_string(&mut blob, exponent);
+    push_string(&mut blob, modulus);
+    (Algorithm::Rsa(unsigned(modulus).to_owned()), blob)
+}
+
+/// Algorithm and ssh public key blob from DSA p, q, g and y (ssh mpints)
+pub fn dsa_public(p: &[u8], q: &[u8], g: &[u8], y: &[u8]) -> (Algorithm, Vec<u8>) {
+    let mut blob = vec![];
+    push_string(&mut blob, b"ssh-dss");
+    push_string(&mut blob, p);
+    push_string(&mut blob, q);
+    push_string(&mut blob, g);
+    push_string(&mut blob, y);
+    (Algorithm::Dsa(unsigned(p).to_owned()), blob)
+}
+
+/// Algorithm and ssh public key blob from a NIST curve name and point
+pub fn ecdsa_public(curve: &str, point: &[u8]) -> (Algorithm, Vec<u8>) {
+    let mut blob = vec![];
+    push_string(&mut blob, format!("ecdsa-sha2-{}", curve).as_bytes());
+    push_string(&mut blob, curve.as_bytes());
+    push_string(&mut blob, point);
+    (Algorithm::Ecdsa(curve.into(), point.to_owned()), blob)
+}
+
+/// Algorithm and ssh public key blob from an Ed25519 point
+pub fn ed25519_public(point: &[u8]) -> (Algorithm, Vec<u8>) {
+    let mut blob = vec![];
+    push_string(&mut blob, b"ssh-ed25519");
+    push_string(&mut blob, point);
+    (Algorithm::Ed25519(point.to_owned()), blob)
+}
+
+/*
+RSAPublicKey ::= SEQUENCE {
+    modulus           INTEGER,  -- n
+    publicExponent    INTEGER   -- e
+}
+*/
+fn rsa_public_key(input: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
+    let key = der(input)?;
+    let items = sequence(&key)?;
+    if items.len() != 2 {
+        return Err("Invalid RSAPublicKey".into());
+    }
+    Ok(rsa_public(slice(&items[0])?, slice(&items[1])?))
+}
+
+/*
+SubjectPublicKeyInfo ::= SEQUENCE {
+    algorithm         AlgorithmIdentifier,
+    subjectPublicKey  BIT STRING
+}
+*/
+/// Algorithm and ssh public key blob of a DER SubjectPublicKeyInfo,
+/// as found in "PUBLIC KEY" files and X.509 certificates
+pub fn subject_public_key_info(input: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
+    let info = der(input)?;
+    let items = sequence(&info)?;
+    if items.len() != 2 {
+        return Err("Invalid SubjectPublicKeyInfo".into());
+    }
+    let (algorithm, parameters) = algorithm_identifier(&items[0])?;
+    let key = slice(&items[1])?;
+    match algorithm.as_str() {
+        "1.2.840.113549.1.1.1" => rsa_public_key(key),
+        "1.2.840.10040.4.1" => {
+            // Dss-Parms are p, q, g and the key is the INTEGER y
+            let parameters = match parameters {
+                Some(parameters) => sequence(parameters)?,
+                None => return Err("DSA key without parameters".into()),
+            };
+            if parameters.len() != 3 {
+                return Err("Invalid DSA parameters".into());
+            }
+            let y = der(key)?;
+            Ok(dsa_public(
+                slice(&parameters[0])?,
+                slice(&parameters[1])?,
+                slice(&parameters[2])?,
+                slice(&y)?,
+            ))
+        }
+        "1.2.840.10045.2.1" => {
+            let curve_oid = match parameters {
+                Some(parameters) => oid(parameters)?,
+                None => return Err("EC key without a named curve".into()),
+            };
+            match ssh_curve(&curve_oid) {
+                Some(curve) => Ok(ecdsa_public(&curve, key)),
+                None => Ok((Algorithm::Unknown, vec![])),
+            }
+        }
+        "1.3.101.112" => Ok(ed25519_public(key)),
+        "1.3.101.110" => Ok((Algorithm::X25519, vec![])),
+        _ => Ok((Algorithm::Unknown, vec![])),
+    }
+}
+
+/// Parse a PEM "PUBLIC KEY" (SubjectPublicKeyInfo) or "RSA PUBLIC KEY" (PKCS#1) file
+pub fn parse_pem(bytes: &[u8]) -> Result<SshKey, String> {
+    let block = nom_pem::decode_block(bytes).map_err(|error| format!("PEM error: {:?}", error))?;
+    let (algorithm, blob) = match block.block_type {
+        "PUBLIC KEY" => subject_public_key_info(&block.data)?,
+        "RSA PUBLIC KEY" => rsa_public_key(&block.data)?,
+        other => return Err(format!("Unsupported PEM block {}", other)),
+    };
+    Ok(SshKey {
+        algorithm,
+        is_public: true,
+        public_blob: blob,
+        format: Some(format!("PEM {}", block.block_type)),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,99 @@
+// Port of OpenSSH's "drunken bishop" visual host key (sshkey.c fingerprint_randomart)
+// http://www.dirk-loss.de/sshvis/drunken_bishop.pdf
+const FIELD_BASE: usize = 8;
+const FIELD_ROWS: usize = FIELD_BASE + 1;
+const FIELD_COLUMNS: usize = FIELD_BASE * 2 + 1;
+const AUGMENTATION: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+// OpenSSH formats the labels into a FIELD_COLUMNS sized C buffer,
+// so they get truncated to leave room for the NUL terminator
+fn border(label: &str) -> String {
+    let mut label = label.to_owned();
+    label.truncate(FIELD_COLUMNS - 1);
+    let mut line = String::from("+");
+    let padding = (FIELD_COLUMNS - label.len()) / 2;
+    line.push_str(&"-".repeat(padding));
+    line.push_str(&label);
+    line.push_str(&"-".repeat(FIELD_COLUMNS - padding - label.len()));
+    line.push('+');
+    line
+}
+
+/// Render the randomart box for a raw fingerprint `digest`.
+/// `key_type` and `bits` make up the header, like `[ED25519 256]`
+/// and `hash_name` the footer, like `[SHA256]`.
+pub fn render(key_type: &str, bits: usize, hash_name: &str, digest: &[u8]) -> String {
+    let max = AUGMENTATION.len() - 1;
+    let mut field = [[0usize; FIELD_ROWS]; FIELD_COLUMNS];
+    let mut x = FIELD_COLUMNS / 2;
+    let mut y = FIELD_ROWS / 2;
+
+    for byte in digest {
+        let mut input = *byte;
+        for _step in 0..4 {
+            if input & 0x1 == 0 {
+                x = x.saturating_sub(1);
+            } else {
+                x = (x + 1).min(FIELD_COLUMNS - 1);
+            }
+            if input & 0x2 == 0 {
+                y = y.saturating_sub(1);
+            } else {
+                y = (y + 1).min(FIELD_ROWS - 1);
+            }
+            if field[x][y] < max - 2 {
+                field[x][y] += 1;
+            }
+            input >>= 2;
+        }
+    }
+    // mark the start (S) and end (E) positions
+    field[FIELD_COLUMNS / 2][FIELD_ROWS / 2] = max - 1;
+    field[x][y] = max;
+
+    let mut title = format!("[{} {}]", key_type, bits);
+    if title.len() > FIELD_COLUMNS {
+        title = format!("[{}]", key_type);
+    }
+    let mut output = border(&title);
+    output.push('\n');
+    for row in 0..FIELD_ROWS {
+        output.push('|');
+        for column in field.iter() {
+            output.push(AUGMENTATION[column[row].min(max)] as char);
+        }
+        output.push_str("|\n");
+    }
+    output.push_str(&border(&format!("[{}]", hash_name)));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn test_render_all_zeros() {
+        // Every step moves up and left, so the bishop ends in the corner
+        assert_eq!(
+            render("ED25519", 256, "SHA256", &[0u8; 32]),
+            "+--[ED25519 256]--+
+|E....            |
+|     .           |
+|      .          |
+|       .         |
+|        S        |
+|                 |
+|                 |
+|                 |
+|                 |
++----[SHA256]-----+"
+        );
+    }
+
+    #[test]
+    fn test_render_long_title_falls_back_to_type() {
+        let art = render("ED25519-CERT", 256, "SHA256", &[]);
+        assert!(art.starts_with("+-[ED25519-CERT]--+\n"));
+    }
+}
@@ -0,0 +1,417 @@
+use nom::{be_u32, be_u64, IResult};
+use public_key;
+use ssh_key::{parse_error, peek_algorithm, push_string, SshKey};
+use std::fmt;
+use time;
+
+// https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL.certkeys
+const CERT_SUFFIX: &str = "-cert-v01@openssh.com";
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum CertificateType {
+    User,
+    Host,
+    Unknown(u32),
+}
+
+impl fmt::Display for CertificateType {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CertificateType::User => write!(out, "user"),
+            CertificateType::Host => write!(out, "host"),
+            CertificateType::Unknown(code) => write!(out, "unknown type {}", code),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SshCertificate {
+    pub key_type: String,
+    pub nonce: Vec<u8>,
+    // The certified public key, with public_blob in plain (non-cert) form
+    pub key: SshKey,
+    pub serial: u64,
+    pub cert_type: CertificateType,
+    pub key_id: String,
+    pub principals: Vec<String>,
+    pub valid_after: u64,
+    pub valid_before: u64,
+    pub critical_options: Vec<(String, String)>,
+    pub extensions: Vec<(String, String)>,
+    pub signature_key: SshKey,
+    pub signature_algorithm: String,
+    pub signature: Vec<u8>,
+}
+
+fn now() -> u64 {
+    time::get_time().sec as u64
+}
+
+fn format_time(seconds: u64) -> String {
+    if seconds > i64::MAX as u64 {
+        return "forever".into();
+    }
+    let tm = time::at_utc(time::Timespec::new(seconds as i64, 0));
+    time::strftime("%Y-%m-%d", &tm).unwrap_or_else(|_| "?".into())
+}
+
+// Compare wire-format blobs when we have them,
+// otherwise fall back to the public/private algorithm details
+fn same_key(certificate_key: &SshKey, key: &SshKey) -> bool {
+    if !key.public_blob.is_empty() {
+        return key.public_blob == certificate_key.public_blob;
+    }
+    certificate_key.is_pair(key)
+}
+
+impl SshCertificate {
+    pub fn is_expired(&self) -> bool {
+        now() >= self.valid_before
+    }
+
+    pub fn is_not_yet_valid(&self) -> bool {
+        now() < self.valid_after
+    }
+
+    /// `key` (usually a private key) is the one this certificate certifies
+    pub fn certifies(&self, key: &SshKey) -> bool {
+        same_key(&self.key, key)
+    }
+
+    /// `key` is the CA key that signed this certificate
+    pub fn is_signed_by(&self, key: &SshKey) -> bool {
+        same_key(&self.signature_key, key)
+    }
+
+    /// The CA signed with the SHA-1 based "ssh-rsa" signature scheme
+    pub fn is_sha1_signed(&self) -> bool {
+        self.signature_algorithm == "ssh-rsa"
+    }
+}
+
+fn join_options(options: &[(String, String)]) -> String {
+    let names: Vec<String> = options
+        .iter()
+        .map(|(name, data)| {
+            if data.is_empty() {
+                name.clone()
+            } else {
+                format!("{}={}", name, data)
+            }
+        })
+        .collect();
+    names.join(", ")
+}
+
+impl fmt::Display for SshCertificate {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        let mut output = String::new();
+        output.push_str(&format!(
+            "ssh {} certificate ({}, key id \"{}\", serial {})",
+            self.cert_type, self.key.algorithm, self.key_id, self.serial
+        ));
+        if let Some(fingerprint) = self.key.fingerprint_sha256() {
+            output.push_str(&format!("\n\tcertifies key {}", fingerprint));
+        }
+        if let Some(fingerprint) = self.signature_key.fingerprint_sha256() {
+            output.push_str(&format!(
+                "\n\tsigned by CA ({}) {} using {}",
+                self.signature_key.algorithm, fingerprint, self.signature_algorithm
+            ));
+        }
+        if !self.principals.is_empty() {
+            output.push_str(&format!("\n\tprincipals: {}", self.principals.join(", ")));
+        }
+        output.push_str(&format!(
+            "\n\tvalid from {} to {}",
+            format_time(self.valid_after),
+            format_time(self.valid_before)
+        ));
+        if !self.critical_options.is_empty() {
+            output.push_str(&format!(
+                "\n\tcritical options: {}",
+                join_options(&self.critical_options)
+            ));
+        }
+        if !self.extensions.is_empty() {
+            output.push_str(&format!(
+                "\n\textensions: {}",
+                join_options(&self.extensions)
+            ));
+        }
+        if self.is_expired() {
+            output.push_str("\n\t🚨 expired");
+        }
+        if self.is_not_yet_valid() {
+            output.push_str("\n\t⚠️ not yet valid");
+        }
+        if self.principals.is_empty() {
+            output.push_str(&format!(
+                "\n\t⚠️ no principals, valid for any {}",
+                self.cert_type
+            ));
+        }
+        if self.is_sha1_signed() {
+            output.push_str("\n\t🚨 CA signature uses ssh-rsa (SHA-1)");
+        }
+        write!(out, "{}", output)
+    }
+}
+
+/// True for key type names like ssh-ed25519-cert-v01@openssh.com
+pub fn is_certificate_type(name: &[u8]) -> bool {
+    name.ends_with(CERT_SUFFIX.as_bytes())
+}
+
+named!(
+    nom_header<(&[u8], &[u8])>,
+    do_parse!(key_type: length_bytes!(be_u32) >> nonce: length_bytes!(be_u32) >> (key_type, nonce))
+);
+
+// The certified public key fields, still in wire format
+fn nom_key_fields(input: &[u8], field_count: usize) -> IResult<&[u8], &[u8]> {
+    recognize!(input, count!(length_bytes!(be_u32), field_count))
+}
+
+/*
+uint64    serial
+uint32    type
+string    key id
+string    valid principals
+uint64    valid after
+uint64    valid before
+string    critical options
+string    extensions
+string    reserved
+string    signature key
+string    signature
+*/
+named!(
+    nom_body<(u64, u32, &[u8], &[u8], u64, u64, &[u8], &[u8], &[u8], &[u8])>,
+    do_parse!(
+        serial: be_u64
+            >> cert_type: be_u32
+            >> key_id: length_bytes!(be_u32)
+            >> principals: length_bytes!(be_u32)
+            >> valid_after: be_u64
+            >> valid_before: be_u64
+            >> critical_options: length_bytes!(be_u32)
+            >> extensions: length_bytes!(be_u32)
+            >> _reserved: length_bytes!(be_u32)
+            >> signature_key: length_bytes!(be_u32)
+            >> signature: length_bytes!(be_u32)
+            >> (
+                serial,
+                cert_type,
+                key_id,
+                principals,
+                valid_after,
+                valid_before,
+                critical_options,
+                extensions,
+                signature_key,
+                signature
+            )
+    )
+);
+
+named!(
+    nom_signature<(&[u8], &[u8])>,
+    do_parse!(format: length_bytes!(be_u32) >> blob: length_bytes!(be_u32) >> (format, blob))
+);
+
+named!(nom_string<&[u8]>, length_bytes!(be_u32));
+
+// A string whose contents are a back-to-back sequence of strings
+fn packed_strings(mut input: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let mut strings = vec![];
+    while !input.is_empty() {
+        let (tail, string) = nom_string(input).map_err(parse_error)?;
+        strings.push(string);
+        input = tail;
+    }
+    Ok(strings)
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// Options are name/data pairs where non-empty data is itself a string
+fn options(input: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let strings = packed_strings(input)?;
+    let mut pairs = vec![];
+    for pair in strings.chunks(2) {
+        if pair.len() != 2 {
+            return Err("Certificate option missing its data".into());
+        }
+        let data = if pair[1].is_empty() {
+            String::new()
+        } else {
+            packed_strings(pair[1])?
+                .iter()
+                .map(|value| lossy(value))
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        pairs.push((lossy(pair[0]), data));
+    }
+    Ok(pairs)
+}
+
+// How many strings/mpints the certified key has, and its plain key type
+fn key_layout(key_type: &str) -> Option<(usize, String)> {
+    let plain = &key_type[..key_type.len() - CERT_SUFFIX.len()];
+    match plain {
+        "ssh-ed25519" => Some((1, plain.into())),
+        "ssh-rsa" => Some((2, plain.into())),
+        "ssh-dss" => Some((4, plain.into())),
+        "ecdsa-sha2-nistp256" | "ecdsa-sha2-nistp384" | "ecdsa-sha2-nistp521" => {
+            Some((2, plain.into()))
+        }
+        // Security keys carry the application after the usual fields
+        "sk-ssh-ed25519" => Some((2, format!("{}@openssh.com", plain))),
+        "sk-ecdsa-sha2-nistp256" => Some((3, format!("{}@openssh.com", plain))),
+        _ => None,
+    }
+}
+
+/// Parse the wire-format certificate blob
+pub fn parse_blob(blob: &[u8]) -> Result<SshCertificate, String> {
+    let (tail, (key_type, nonce)) = nom_header(blob).map_err(parse_error)?;
+    if !is_certificate_type(key_type) {
+        return Err("Not an OpenSSH certificate".into());
+    }
+    let key_type = lossy(key_type);
+    let (field_count, plain_type) = match key_layout(&key_type) {
+        Some(layout) => layout,
+        None => return Err(format!("Unsupported certificate type {}", key_type)),
+    };
+    let (tail, key_fields) = nom_key_fields(tail, field_count).map_err(parse_error)?;
+    let (_tail, body) = nom_body(tail).map_err(parse_error)?;
+    let (
+        serial,
+        cert_type,
+        key_id,
+        principals,
+        valid_after,
+        valid_before,
+        critical_options,
+        extensions,
+        signature_key,
+        signature,
+    ) = body;
+    let (_tail, (signature_algorithm, signature)) =
+        nom_signature(signature).map_err(parse_error)?;
+
+    let mut public_blob = vec![];
+    push_string(&mut public_blob, plain_type.as_bytes());
+    public_blob.extend_from_slice(key_fields);
+    let key = SshKey {
+        algorithm: peek_algorithm(false, &public_blob)?,
+        is_public: true,
+        public_blob,
+        ..Default::default()
+    };
+
+    let ca_key = SshKey {
+        algorithm: peek_algorithm(false, signature_key)?,
+        is_public: true,
+        public_blob: signature_key.to_owned(),
+        ..Default::default()
+    };
+
+    Ok(SshCertificate {
+        key_type: key_type.clone(),
+        nonce: nonce.to_owned(),
+        key,
+        serial,
+        cert_type: match cert_type {
+            1 => CertificateType::User,
+            2 => CertificateType::Host,
+            code => CertificateType::Unknown(code),
+        },
+        key_id: lossy(key_id),
+        principals: packed_strings(principals)?
+            .iter()
+            .map(|principal| lossy(principal))
+            .collect(),
+        valid_after,
+        valid_before,
+        critical_options: options(critical_options)?,
+        extensions: options(extensions)?,
+        signature_key: ca_key,
+        signature_algorithm: lossy(signature_algorithm),
+        signature: signature.to_owned(),
+    })
+}
+
+/// Parse a one-line certificate file like id_ed25519-cert.pub
+pub fn parse(bytes: &[u8]) -> Result<SshCertificate, String> {
+    let (blob, comment) = public_key::decode(bytes)?;
+    let mut certificate = parse_blob(&blob)?;
+    certificate.key.comment = Some(comment);
+    Ok(certificate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{options, packed_strings, SshCertificate};
+    use ssh_key::push_string;
+
+    #[test]
+    fn test_packed_strings() {
+        let mut packed = vec![];
+        push_string(&mut packed, b"alice");
+        push_string(&mut packed, b"deploy");
+        assert_eq!(
+            packed_strings(&packed).unwrap(),
+            vec![&b"alice"[..], &b"deploy"[..]]
+        );
+        assert!(packed_strings(&packed[..7]).is_err());
+    }
+
+    #[test]
+    fn test_options() {
+        let mut data = vec![];
+        push_string(&mut data, b"/usr/bin/true");
+        let mut packed = vec![];
+        push_string(&mut packed, b"force-command");
+        push_string(&mut packed, &data);
+        push_string(&mut packed, b"permit-pty");
+        push_string(&mut packed, b"");
+        assert_eq!(
+            options(&packed).unwrap(),
+            vec![
+                ("force-command".into(), "/usr/bin/true".into()),
+                ("permit-pty".into(), "".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validity_window() {
+        let mut certificate = SshCertificate {
+            key_type: "ssh-ed25519-cert-v01@openssh.com".into(),
+            nonce: vec![],
+            key: Default::default(),
+            serial: 0,
+            cert_type: super::CertificateType::User,
+            key_id: "".into(),
+            principals: vec![],
+            valid_after: 0,
+            valid_before: u64::MAX,
+            critical_options: vec![],
+            extensions: vec![],
+            signature_key: Default::default(),
+            signature_algorithm: "ssh-ed25519".into(),
+            signature: vec![],
+        };
+        assert!(!certificate.is_expired());
+        assert!(!certificate.is_not_yet_valid());
+        certificate.valid_before = 1;
+        assert!(certificate.is_expired());
+        certificate.valid_after = u64::MAX - 1;
+        assert!(certificate.is_not_yet_valid());
+    }
+}
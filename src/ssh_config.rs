@@ -0,0 +1,803 @@
+// ssh_config(5) files: ~/.ssh/config, /etc/ssh/ssh_config and what they Include
+use known_hosts::wildcard_match;
+use leaf::Leaf;
+use rsfs::{DirEntry, GenFS, Metadata};
+use std::env;
+use std::fmt;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+// OpenSSH gives up on Includes nested deeper than this
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// The identity files ssh tries, in order, for hosts without an IdentityFile
+pub const DEFAULT_IDENTITY_FILES: [&str; 7] = [
+    "id_rsa",
+    "id_ecdsa",
+    "id_ecdsa_sk",
+    "id_ed25519",
+    "id_ed25519_sk",
+    "id_xmss",
+    "id_dsa",
+];
+
+/// A block that limits which connections the directives below it apply to
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    // Host patterns, like `*.example.com !bastion.example.com`
+    Host(Vec<String>),
+    // Match criteria with their arguments, like `host *.corp user git`
+    Match(Vec<String>),
+}
+
+/// The local account, for `~` and the %d and %u tokens
+#[derive(Clone, Debug, Default)]
+pub struct Local {
+    pub home: PathBuf,
+    pub user: String,
+}
+
+impl Local {
+    /// From $HOME and $USER (or $LOGNAME)
+    pub fn from_env() -> Local {
+        Local {
+            home: env::home_dir().unwrap_or_default(),
+            user: env::var("USER")
+                .or_else(|_| env::var("LOGNAME"))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// One `keyword arguments` line
+#[derive(Debug)]
+pub struct Directive {
+    pub file: PathBuf,
+    // 1-based
+    pub line: usize,
+    // Lowercased, keywords are case-insensitive
+    pub keyword: String,
+    pub arguments: Vec<String>,
+    // Every Host or Match block this line is in, the one around an Include first
+    pub conditions: Vec<Condition>,
+}
+
+/// An IdentityFile or CertificateFile with `~` and its tokens expanded
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    pub file: PathBuf,
+    pub line: usize,
+    // "IdentityFile" or "CertificateFile", as written
+    pub keyword: String,
+    pub path: PathBuf,
+}
+
+/// A connection being configured, for the %h, %n and %r tokens
+#[derive(Debug)]
+struct Target<'a> {
+    alias: &'a str,
+    hostname: String,
+    user: String,
+}
+
+#[derive(Debug, Default)]
+pub struct SshConfig {
+    // In the order ssh reads them, Includes expanded in place
+    pub directives: Vec<Directive>,
+    // File, line and message for what couldn't be read
+    pub errors: Vec<(PathBuf, usize, String)>,
+}
+
+/// The `config` file in a `.ssh` directory, or any `ssh_config`
+pub fn is_ssh_config(path: &Path) -> bool {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some("ssh_config") => true,
+        Some("config") => path
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .map(|name| name == ".ssh")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+// `keyword arguments` or `keyword=arguments`, arguments may be double-quoted
+fn split_line(line: &str) -> Result<Option<(String, Vec<String>)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let end = line
+        .find(|character: char| character.is_whitespace() || character == '=')
+        .unwrap_or(line.len());
+    let keyword = line[..end].to_lowercase();
+    let mut rest = line[end..].trim_start();
+    if let Some(value) = rest.strip_prefix('=') {
+        rest = value.trim_start();
+    }
+    let mut arguments = vec![];
+    let mut argument = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    for character in rest.chars() {
+        match character {
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            _ if character.is_whitespace() && !in_quotes => {
+                if !argument.is_empty() || quoted {
+                    arguments.push(argument.clone());
+                }
+                argument.clear();
+                quoted = false;
+            }
+            _ => argument.push(character),
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quote".into());
+    }
+    if !argument.is_empty() || quoted {
+        arguments.push(argument);
+    }
+    Ok(Some((keyword, arguments)))
+}
+
+fn expand_tilde(value: &str, local: &Local) -> PathBuf {
+    if value == "~" {
+        local.home.clone()
+    } else if let Some(relative) = value.strip_prefix("~/") {
+        local.home.join(relative)
+    } else {
+        PathBuf::from(value)
+    }
+}
+
+// `~` and the %d, %u, %h, %n, %r and %% tokens. Host tokens stay
+// unexpanded without a `target`.
+fn expand(value: &str, local: &Local, target: Option<&Target>) -> PathBuf {
+    let mut expanded = String::new();
+    let mut characters = value.chars();
+    while let Some(character) = characters.next() {
+        if character != '%' {
+            expanded.push(character);
+            continue;
+        }
+        match (characters.next(), target) {
+            (Some('%'), _) => expanded.push('%'),
+            (Some('d'), _) => expanded.push_str(&local.home.to_string_lossy()),
+            (Some('u'), _) => expanded.push_str(&local.user),
+            (Some('h'), Some(target)) => expanded.push_str(&target.hostname),
+            (Some('n'), Some(target)) => expanded.push_str(target.alias),
+            (Some('r'), Some(target)) => expanded.push_str(&target.user),
+            (Some(token), _) => {
+                expanded.push('%');
+                expanded.push(token);
+            }
+            (None, _) => expanded.push('%'),
+        }
+    }
+    expand_tilde(&expanded, local)
+}
+
+fn has_host_token(value: &str) -> bool {
+    value.contains("%h") || value.contains("%n") || value.contains("%r")
+}
+
+// `*` and `?` in file names, case-sensitive unlike host patterns
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+// The existing files matching a path with wildcards, sorted like glob(3)
+fn glob<F: GenFS>(fs: &F, pattern: &Path) -> Vec<PathBuf> {
+    let mut matches = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let name = match component {
+            Component::Normal(name) => name.to_string_lossy().into_owned(),
+            other => {
+                for path in &mut matches {
+                    path.push(other.as_os_str());
+                }
+                continue;
+            }
+        };
+        if !name.contains(&['*', '?'][..]) {
+            for path in &mut matches {
+                path.push(&name);
+            }
+            continue;
+        }
+        let mut expanded = vec![];
+        for directory in &matches {
+            let entries = match fs.read_dir(directory) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut names: Vec<String> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                // Like the shell, wildcards don't match hidden files
+                .filter(|entry| {
+                    !entry.starts_with('.') && glob_match(name.as_bytes(), entry.as_bytes())
+                })
+                .collect();
+            names.sort();
+            for entry in names {
+                expanded.push(directory.join(entry));
+            }
+        }
+        matches = expanded;
+    }
+    matches
+        .into_iter()
+        .filter(|path| {
+            fs.metadata(path)
+                .map(|meta| meta.is_file())
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+fn read_file<F: GenFS>(
+    fs: &F,
+    path: &Path,
+    include_dir: &Path,
+    local: &Local,
+    outer: &[Condition],
+    depth: usize,
+    config: &mut SshConfig,
+) -> Result<(), String> {
+    let mut bytes = vec![];
+    fs.open_file(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|error| format!("Error reading {}: {}", path.display(), error))?;
+    let mut conditions = outer.to_vec();
+    for (index, line) in String::from_utf8_lossy(&bytes).lines().enumerate() {
+        let (keyword, arguments) = match split_line(line) {
+            Ok(Some(directive)) => directive,
+            Ok(None) => continue,
+            Err(error) => {
+                config.errors.push((path.to_path_buf(), index + 1, error));
+                continue;
+            }
+        };
+        match keyword.as_str() {
+            "host" => {
+                conditions = outer.to_vec();
+                conditions.push(Condition::Host(arguments));
+            }
+            "match" => {
+                conditions = outer.to_vec();
+                conditions.push(Condition::Match(arguments));
+            }
+            "include" => {
+                for argument in &arguments {
+                    // Relative to ~/.ssh or /etc/ssh, whichever config started it
+                    let pattern = include_dir.join(expand_tilde(argument, local));
+                    for included in glob(fs, &pattern) {
+                        let result = if depth >= MAX_INCLUDE_DEPTH {
+                            Err(format!("Include nested too deeply: {}", included.display()))
+                        } else {
+                            read_file(
+                                fs,
+                                &included,
+                                include_dir,
+                                local,
+                                &conditions,
+                                depth + 1,
+                                config,
+                            )
+                        };
+                        if let Err(error) = result {
+                            config.errors.push((path.to_path_buf(), index + 1, error));
+                        }
+                    }
+                }
+            }
+            _ => config.directives.push(Directive {
+                file: path.to_path_buf(),
+                line: index + 1,
+                keyword,
+                arguments,
+                conditions: conditions.clone(),
+            }),
+        }
+    }
+    Ok(())
+}
+
+/// Read ssh_config files in the order ssh does, usually ~/.ssh/config
+/// then /etc/ssh/ssh_config, following their Includes
+pub fn read<F: GenFS>(fs: &F, paths: &[PathBuf], local: &Local) -> SshConfig {
+    let mut config: SshConfig = Default::default();
+    for path in paths {
+        let include_dir = path.parent().unwrap_or_else(|| Path::new("/"));
+        if let Err(error) = read_file(fs, path, include_dir, local, &[], 0, &mut config) {
+            config.errors.push((path.clone(), 0, error));
+        }
+    }
+    config
+}
+
+// A comma- or space-separated pattern list, false as soon as a negated pattern matches
+fn pattern_list_match<'a, I: Iterator<Item = &'a String>>(patterns: I, name: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.flat_map(|patterns| patterns.split(',')) {
+        if pattern.starts_with('!') {
+            if wildcard_match(&pattern.as_bytes()[1..], name.as_bytes()) {
+                return false;
+            }
+        } else if wildcard_match(pattern.as_bytes(), name.as_bytes()) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+impl Condition {
+    /// Whether `ssh alias` enters this block, connecting as `user` on the
+    /// remote host. Match criteria that need a live connection, like `exec`
+    /// and `localnetwork`, never match.
+    pub fn matches(&self, alias: &str, user: &str, local: &Local) -> bool {
+        match *self {
+            Condition::Host(ref patterns) => pattern_list_match(patterns.iter(), alias),
+            Condition::Match(ref criteria) => {
+                let mut criteria = criteria.iter();
+                while let Some(criterion) = criteria.next() {
+                    let lowercase = criterion.to_lowercase();
+                    let (negated, name) = match lowercase.strip_prefix('!') {
+                        Some(name) => (true, name),
+                        None => (false, &lowercase[..]),
+                    };
+                    let matched = match name {
+                        "all" | "final" => true,
+                        "canonical" => false,
+                        "host" | "originalhost" => {
+                            criteria
+                                .next()
+                                .map(|list| pattern_list_match(Some(list).into_iter(), alias))
+                                == Some(true)
+                        }
+                        "user" => {
+                            criteria
+                                .next()
+                                .map(|list| pattern_list_match(Some(list).into_iter(), user))
+                                == Some(true)
+                        }
+                        "localuser" => {
+                            criteria
+                                .next()
+                                .map(|list| pattern_list_match(Some(list).into_iter(), &local.user))
+                                == Some(true)
+                        }
+                        _ => {
+                            if name != "localnetwork" {
+                                criteria.next();
+                            }
+                            false
+                        }
+                    };
+                    if matched == negated {
+                        return false;
+                    }
+                }
+                true
+            }
+        }
+    }
+}
+
+impl SshConfig {
+    /// The names in Host lines that aren't patterns, like `github` in `Host github`
+    pub fn host_aliases(&self) -> Vec<String> {
+        let mut aliases: Vec<String> = vec![];
+        for directive in &self.directives {
+            for condition in &directive.conditions {
+                if let Condition::Host(ref patterns) = *condition {
+                    for pattern in patterns {
+                        let is_alias = !pattern.contains(|character| {
+                            character == '*' || character == '?' || character == '!'
+                        });
+                        if is_alias && !aliases.contains(pattern) {
+                            aliases.push(pattern.clone());
+                        }
+                    }
+                }
+            }
+        }
+        aliases
+    }
+
+    fn applicable<'a>(
+        &'a self,
+        alias: &'a str,
+        local: &'a Local,
+    ) -> impl Iterator<Item = &'a Directive> + 'a {
+        // Like ssh, `Match user` sees the User set before its block, or the
+        // local user, and a block is only evaluated once, on entering it
+        let mut user: Option<&'a str> = None;
+        let mut block: Option<(&'a [Condition], bool)> = None;
+        self.directives.iter().filter(move |directive| {
+            let applies = match block {
+                Some((conditions, applies)) if conditions == &directive.conditions[..] => applies,
+                _ => {
+                    let remote_user = user.unwrap_or(&local.user);
+                    let applies = directive
+                        .conditions
+                        .iter()
+                        .all(|condition| condition.matches(alias, remote_user, local));
+                    block = Some((&directive.conditions, applies));
+                    applies
+                }
+            };
+            if applies && user.is_none() && directive.keyword == "user" {
+                user = directive.arguments.first().map(|user| user.as_str());
+            }
+            applies
+        })
+    }
+
+    // The first value wins, as in ssh
+    fn first_value(&self, alias: &str, local: &Local, keyword: &str) -> Option<String> {
+        self.applicable(alias, local)
+            .find(|directive| directive.keyword == keyword)
+            .and_then(|directive| directive.arguments.first().cloned())
+    }
+
+    fn target<'a>(&self, alias: &'a str, local: &Local) -> Target<'a> {
+        Target {
+            alias,
+            hostname: self
+                .first_value(alias, local, "hostname")
+                .map(|hostname| hostname.replace("%h", alias))
+                .unwrap_or_else(|| alias.to_owned()),
+            user: self
+                .first_value(alias, local, "user")
+                .unwrap_or_else(|| local.user.clone()),
+        }
+    }
+
+    /// The IdentityFiles `ssh alias` would try, in order. Unlike most
+    /// options every IdentityFile counts, not just the first.
+    pub fn identity_files(&self, alias: &str, local: &Local) -> Vec<Reference> {
+        let target = self.target(alias, local);
+        self.applicable(alias, local)
+            .filter(|directive| directive.keyword == "identityfile")
+            .filter_map(|directive| {
+                directive
+                    .arguments
+                    .first()
+                    .filter(|value| !value.eq_ignore_ascii_case("none"))
+                    .map(|value| reference(directive, expand(value, local, Some(&target))))
+            })
+            .collect()
+    }
+
+    /// Risky client options, each with the file and line it's on. Inside a
+    /// Host or Match block that doesn't cover every host, they are one
+    /// severity lower.
+    pub fn audit(&self) -> Vec<Finding> {
+        let mut findings = vec![];
+        for directive in &self.directives {
+            let (severity, keyword, reason) = match risk(directive) {
+                Some(risk) => risk,
+                None => continue,
+            };
+            let severity = match (applies_to_all(&directive.conditions), severity) {
+                (true, severity) => severity,
+                (false, Severity::High) => Severity::Medium,
+                (false, _) => Severity::Low,
+            };
+            findings.push(Finding {
+                severity,
+                file: directive.file.clone(),
+                line: directive.line,
+                message: format!(
+                    "{} {} for {}, {}",
+                    keyword,
+                    directive.arguments.join(" "),
+                    scope(&directive.conditions),
+                    reason
+                ),
+            });
+        }
+        findings
+    }
+
+    /// Every IdentityFile and CertificateFile, expanded for each host alias
+    /// they apply to when they use a host token
+    pub fn references(&self, local: &Local) -> Vec<Reference> {
+        let aliases = self.host_aliases();
+        let mut references: Vec<Reference> = vec![];
+        for directive in &self.directives {
+            if directive.keyword != "identityfile" && directive.keyword != "certificatefile" {
+                continue;
+            }
+            let value = match directive.arguments.first() {
+                Some(value) if !value.eq_ignore_ascii_case("none") => value,
+                _ => continue,
+            };
+            if !has_host_token(value) {
+                references.push(reference(directive, expand(value, local, None)));
+                continue;
+            }
+            for alias in &aliases {
+                let target = self.target(alias, local);
+                if directive
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.matches(alias, &target.user, local))
+                {
+                    let expanded = reference(directive, expand(value, local, Some(&target)));
+                    if !references.contains(&expanded) {
+                        references.push(expanded);
+                    }
+                }
+            }
+        }
+        references
+    }
+}
+
+fn reference(directive: &Directive, path: PathBuf) -> Reference {
+    Reference {
+        file: directive.file.clone(),
+        line: directive.line,
+        keyword: if directive.keyword == "identityfile" {
+            "IdentityFile".into()
+        } else {
+            "CertificateFile".into()
+        },
+        path,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        let (emoji, name) = match *self {
+            Severity::Low => ("ℹ️", "low"),
+            Severity::Medium => ("⚠️", "medium"),
+            Severity::High => ("🚨", "high"),
+        };
+        write!(out, "{} {}", emoji, name)
+    }
+}
+
+/// A risky client option found by `SshConfig::audit`
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+// No block at all, `Host *` or `Match all`
+fn applies_to_all(conditions: &[Condition]) -> bool {
+    conditions.iter().all(|condition| match *condition {
+        Condition::Host(ref patterns) => patterns.iter().any(|pattern| pattern == "*"),
+        Condition::Match(ref criteria) => {
+            criteria.len() == 1 && criteria[0].eq_ignore_ascii_case("all")
+        }
+    })
+}
+
+fn scope(conditions: &[Condition]) -> String {
+    if applies_to_all(conditions) {
+        return "all hosts".into();
+    }
+    let blocks: Vec<String> = conditions
+        .iter()
+        .map(|condition| match *condition {
+            Condition::Host(ref patterns) => format!("Host {}", patterns.join(" ")),
+            Condition::Match(ref criteria) => format!("Match {}", criteria.join(" ")),
+        })
+        .collect();
+    blocks.join(", ")
+}
+
+// How risky a directive is for every host, its keyword as documented and why
+fn risk(directive: &Directive) -> Option<(Severity, &'static str, &'static str)> {
+    let value = directive
+        .arguments
+        .first()
+        .map(|value| value.to_lowercase())?;
+    match directive.keyword.as_str() {
+        // yes, or the agent socket to forward
+        "forwardagent" if value != "no" && value != "false" => Some((
+            Severity::High,
+            "ForwardAgent",
+            "root on the remote host can use your agent's keys",
+        )),
+        "stricthostkeychecking" if value == "no" || value == "off" => Some((
+            Severity::High,
+            "StrictHostKeyChecking",
+            "unknown host keys are accepted without asking",
+        )),
+        "userknownhostsfile" | "globalknownhostsfile"
+            if directive.arguments.iter().any(|file| file == "/dev/null") =>
+        {
+            Some((
+                Severity::High,
+                if directive.keyword == "userknownhostsfile" {
+                    "UserKnownHostsFile"
+                } else {
+                    "GlobalKnownHostsFile"
+                },
+                "host keys are never remembered, so every connection can be intercepted",
+            ))
+        }
+        "pubkeyacceptedalgorithms"
+        | "pubkeyacceptedkeytypes"
+        | "hostkeyalgorithms"
+        | "casignaturealgorithms"
+            if !value.starts_with('-') =>
+        {
+            let keyword = match directive.keyword.as_str() {
+                "pubkeyacceptedalgorithms" => "PubkeyAcceptedAlgorithms",
+                "pubkeyacceptedkeytypes" => "PubkeyAcceptedKeyTypes",
+                "hostkeyalgorithms" => "HostKeyAlgorithms",
+                _ => "CASignatureAlgorithms",
+            };
+            let algorithms: Vec<&str> = value.trim_start_matches(['+', '^']).split(',').collect();
+            if algorithms.contains(&"ssh-dss") {
+                Some((Severity::Medium, keyword, "re-enables DSA keys"))
+            } else if algorithms.contains(&"ssh-rsa") {
+                Some((
+                    Severity::Medium,
+                    keyword,
+                    "re-enables SHA-1 ssh-rsa signatures",
+                ))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// How the config and the keys found by `scan` line up
+#[derive(Debug, Default)]
+pub struct KeyUsage {
+    // IdentityFile and CertificateFile references to files that don't exist
+    pub missing: Vec<Reference>,
+    // Private keys in ~/.ssh no IdentityFile names. The default id_* names
+    // don't count, ssh tries those for every host without an IdentityFile.
+    pub unused: Vec<PathBuf>,
+    // Each host alias and the identity file ssh would try first, if any exists
+    pub first_keys: Vec<(String, Option<PathBuf>)>,
+}
+
+fn exists<F: GenFS>(fs: &F, path: &Path) -> bool {
+    fs.metadata(path).is_ok()
+}
+
+/// Cross-reference the IdentityFiles and CertificateFiles of `config`
+/// with the `Leaf::SshKey` results of `scan`
+pub fn key_usage<F: GenFS>(fs: &F, config: &SshConfig, local: &Local, leaves: &[Leaf]) -> KeyUsage {
+    let mut usage: KeyUsage = Default::default();
+    let references = config.references(local);
+    for reference in &references {
+        if !exists(fs, &reference.path) {
+            usage.missing.push(reference.clone());
+        }
+    }
+
+    let defaults: Vec<PathBuf> = DEFAULT_IDENTITY_FILES
+        .iter()
+        .map(|name| local.home.join(".ssh").join(name))
+        .collect();
+    let ssh_dir = local.home.join(".ssh");
+    for leaf in leaves {
+        let path = match *leaf {
+            Leaf::SshKey(ref path, ref key) if !key.is_public => path,
+            Leaf::SshKeys(ref path, _) => path,
+            _ => continue,
+        };
+        // Keys elsewhere, like TLS keys, are none of ssh's business
+        if !path.starts_with(&ssh_dir) {
+            continue;
+        }
+        // An IdentityFile may name the .pub file of a key kept in an agent
+        let mut public = path.clone().into_os_string();
+        public.push(".pub");
+        let is_used = defaults.contains(path)
+            || references.iter().any(|reference| {
+                reference.keyword == "IdentityFile"
+                    && (reference.path == *path || reference.path.as_os_str() == public)
+            });
+        if !is_used && !usage.unused.contains(path) {
+            usage.unused.push(path.clone());
+        }
+    }
+
+    for alias in config.host_aliases() {
+        let identity_files: Vec<PathBuf> = config
+            .identity_files(&alias, local)
+            .into_iter()
+            .map(|reference| reference.path)
+            .collect();
+        let candidates = if identity_files.is_empty() {
+            defaults.clone()
+        } else {
+            identity_files
+        };
+        let first = candidates.into_iter().find(|path| exists(fs, path));
+        usage.first_keys.push((alias, first));
+    }
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, split_line, Condition, Local, Target};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_split_line() {
+        assert_eq!(
+            split_line("  IdentityFile \"~/.ssh/my key\" "),
+            Ok(Some(("identityfile".into(), vec!["~/.ssh/my key".into()])))
+        );
+        assert_eq!(
+            split_line("Port=2222"),
+            Ok(Some(("port".into(), vec!["2222".into()])))
+        );
+        assert_eq!(split_line("# comment"), Ok(None));
+        assert!(split_line("ProxyCommand \"ssh -W").is_err());
+    }
+
+    #[test]
+    fn test_expand() {
+        let local = Local {
+            home: PathBuf::from("/home/a"),
+            user: "a".into(),
+        };
+        let target = Target {
+            alias: "gh",
+            hostname: "github.com".into(),
+            user: "git".into(),
+        };
+        assert_eq!(
+            expand("~/.ssh/%u@%h_%r", &local, Some(&target)),
+            PathBuf::from("/home/a/.ssh/a@github.com_git")
+        );
+        assert_eq!(
+            expand("%d/keys/%n%%", &local, None),
+            PathBuf::from("/home/a/keys/%n%")
+        );
+    }
+
+    #[test]
+    fn test_match_condition() {
+        let local = Local {
+            user: "a".into(),
+            ..Default::default()
+        };
+        let condition =
+            |criteria: &str| Condition::Match(criteria.split(' ').map(String::from).collect());
+        assert!(condition("host *.corp,!jump.corp user a").matches("db.corp", "a", &local));
+        assert!(!condition("host *.corp,!jump.corp").matches("jump.corp", "a", &local));
+        assert!(!condition("exec true").matches("db.corp", "a", &local));
+        assert!(condition("!exec true all").matches("db.corp", "a", &local));
+        assert!(!condition("user a").matches("db.corp", "deploy", &local));
+        assert!(condition("user deploy localuser a").matches("db.corp", "deploy", &local));
+    }
+}
@@ -1,7 +1,54 @@
+use base64;
+use md5::Md5;
 use nom;
 use nom::be_u32;
+use randomart;
+use sha2::{Digest, Sha256};
 use std::fmt;
 
+// Flag bits for FIDO/U2F security keys, see PROTOCOL.u2f in OpenSSH
+const SK_USER_PRESENCE_REQD: u8 = 0x01;
+const SK_USER_VERIFICATION_REQD: u8 = 0x04;
+const SK_RESIDENT_KEY: u8 = 0x20;
+
+/// Extra details of a FIDO/U2F security key (sk-*@openssh.com)
+#[derive(PartialEq, Eq, Debug, Default)]
+pub struct SecurityKey {
+    pub application: String,
+    // Only the private key stub knows the flags and key handle
+    pub flags: Option<u8>,
+    pub key_handle_length: Option<usize>,
+}
+
+impl SecurityKey {
+    pub fn new(application: &[u8]) -> Self {
+        Self {
+            application: String::from_utf8_lossy(application).into(),
+            flags: None,
+            key_handle_length: None,
+        }
+    }
+
+    fn has_flag(&self, flag: u8) -> Option<bool> {
+        self.flags.map(|flags| flags & flag != 0)
+    }
+
+    /// Touching the key is required (false means no-touch-required)
+    pub fn user_presence_required(&self) -> Option<bool> {
+        self.has_flag(SK_USER_PRESENCE_REQD)
+    }
+
+    /// A PIN or biometric check is required
+    pub fn user_verification_required(&self) -> Option<bool> {
+        self.has_flag(SK_USER_VERIFICATION_REQD)
+    }
+
+    /// The key handle is stored on the token itself (ssh-keygen -O resident)
+    pub fn is_resident(&self) -> Option<bool> {
+        self.has_flag(SK_RESIDENT_KEY)
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 pub enum Algorithm {
     Unknown,
@@ -9,6 +56,10 @@ pub enum Algorithm {
     Rsa(Vec<u8>),
     Ecdsa(String, Vec<u8>),
     Dsa(Vec<u8>),
+    SkEd25519(Vec<u8>, SecurityKey),
+    SkEcdsa(String, Vec<u8>, SecurityKey),
+    // Key agreement only, so there is no ssh public key to pair with
+    X25519,
 }
 
 impl fmt::Display for Algorithm {
@@ -16,19 +67,106 @@ impl fmt::Display for Algorithm {
         match *self {
             Algorithm::Ed25519(_) => write!(out, "ed25519"),
             Algorithm::Rsa(_) => write!(out, "rsa"),
+            // The curve of an encrypted PEM key is unknown
+            Algorithm::Ecdsa(ref curve, _) if curve.is_empty() => write!(out, "ecdsa"),
             Algorithm::Ecdsa(ref curve, _) => write!(out, "ecdsa, curve {}", curve),
             Algorithm::Dsa(_) => write!(out, "dsa"),
+            Algorithm::SkEd25519(_, ref security_key) => {
+                write!(out, "ed25519-sk, application {}", security_key.application)
+            }
+            Algorithm::SkEcdsa(ref curve, _, ref security_key) => write!(
+                out,
+                "ecdsa-sk, curve {}, application {}",
+                curve, security_key.application
+            ),
+            Algorithm::X25519 => write!(out, "x25519"),
             _ => write!(out, "unknown"),
         }
     }
 }
 
+/// How the passphrase of an encrypted private key becomes the cipher key
+#[derive(PartialEq, Eq, Debug)]
+pub enum Kdf {
+    // salt length, rounds
+    Bcrypt(usize, u32),
+    // Legacy PEM encryption: one round of MD5 over the passphrase and 8 byte salt
+    OpensslMd5,
+    // PKCS#8 PBES2: prf, iterations, salt length
+    Pbkdf2(String, u32, usize),
+    // PKCS#8 PBES2: cost (N), block size (r), parallelization (p)
+    Scrypt(u64, u32, u32),
+    // PKCS#8 PBES1 and the similar PKCS#12 schemes, by name
+    Pbes1(String),
+    // PuTTY .ppk v3: variant, memory in KiB, passes, parallelism
+    Argon2(String, u32, u32, u32),
+    // PuTTY .ppk v2: unsalted SHA-1 of the passphrase
+    PuttySha1,
+    // ssh.com: unsalted MD5 chain over the passphrase
+    SshcomMd5,
+    Unknown(String),
+}
+
+/// PBKDF2 iteration count below which PKCS#8 keys get a warning
+pub const MIN_PBKDF2_ITERATIONS: u32 = 10_000;
+
+impl fmt::Display for Kdf {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Kdf::Bcrypt(salt_length, rounds) => write!(
+                out,
+                "bcrypt kdf, {} rounds, {} byte salt",
+                rounds, salt_length
+            ),
+            Kdf::OpensslMd5 => write!(out, "openssl md5 kdf, 1 round, 8 byte salt"),
+            Kdf::Pbkdf2(ref prf, iterations, salt_length) => write!(
+                out,
+                "pbkdf2 kdf, {}, {} iterations, {} byte salt",
+                prf, iterations, salt_length
+            ),
+            Kdf::Scrypt(cost, block_size, parallelization) => write!(
+                out,
+                "scrypt kdf, N={}, r={}, p={}",
+                cost, block_size, parallelization
+            ),
+            Kdf::Pbes1(ref name) => write!(out, "legacy pbes1 scheme {}", name),
+            Kdf::Argon2(ref variant, memory, passes, parallelism) => write!(
+                out,
+                "{} kdf, {} KiB memory, {} passes, parallelism {}",
+                variant.to_lowercase(),
+                memory,
+                passes,
+                parallelism
+            ),
+            Kdf::PuttySha1 => write!(out, "putty sha1 kdf, 1 round, no salt"),
+            Kdf::SshcomMd5 => write!(out, "ssh.com md5 kdf, 1 round, no salt"),
+            Kdf::Unknown(ref name) => write!(out, "unknown kdf {}", name),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SshKey {
     pub algorithm: Algorithm,
     pub comment: Option<String>,
     pub is_encrypted: bool,
     pub is_public: bool,
+    // Full ssh wire-format public key blob, as found base64-encoded
+    // in a .pub file. Empty when it can't be determined (encrypted PEM).
+    pub public_blob: Vec<u8>,
+    // Problems found while parsing, like a corrupt private section
+    pub errors: Vec<String>,
+    // Only known for encrypted keys
+    pub cipher: Option<String>,
+    pub kdf: Option<Kdf>,
+    // Legacy PEM encryption stores the IV in its DEK-Info header
+    pub cipher_iv: Option<Vec<u8>>,
+    // Encrypted, but we had the passphrase so all the details are known
+    pub is_decrypted: bool,
+    // Obsolete file format that should be converted, like "ssh.com"
+    pub legacy_format: Option<String>,
+    // File format when it isn't OpenSSH's own, like "PEM PUBLIC KEY"
+    pub format: Option<String>,
 }
 
 impl SshKey {
@@ -38,6 +176,96 @@ impl SshKey {
             comment: None,
             is_encrypted: false,
             is_public: false,
+            public_blob: vec![],
+            errors: vec![],
+            cipher: None,
+            kdf: None,
+            cipher_iv: None,
+            is_decrypted: false,
+            legacy_format: None,
+            format: None,
+        }
+    }
+
+    /// OpenSSH-style SHA256 fingerprint like `ssh-keygen -l` prints
+    pub fn fingerprint_sha256(&self) -> Option<String> {
+        if self.public_blob.is_empty() {
+            return None;
+        }
+        let digest = Sha256::digest(&self.public_blob);
+        let config = base64::Config::new(
+            base64::CharacterSet::Standard,
+            false,
+            false,
+            base64::LineWrap::NoWrap,
+        );
+        Some(format!("SHA256:{}", base64::encode_config(&digest, config)))
+    }
+
+    /// Legacy colon-separated MD5 fingerprint like `ssh-keygen -l -E md5` prints
+    pub fn fingerprint_md5(&self) -> Option<String> {
+        if self.public_blob.is_empty() {
+            return None;
+        }
+        let digest = Md5::digest(&self.public_blob);
+        let hex: Vec<String> = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        Some(format!("MD5:{}", hex.join(":")))
+    }
+
+    /// OpenSSH "drunken bishop" randomart like `ssh-keygen -lv` prints,
+    /// driven by the SHA256 fingerprint
+    pub fn randomart(&self) -> Option<String> {
+        if self.public_blob.is_empty() {
+            return None;
+        }
+        // Encrypted keys only carry a placeholder algorithm, but the blob has it all
+        let (key_type, bits) = match peek_algorithm(false, &self.public_blob) {
+            Ok(Algorithm::Ed25519(_)) => ("ED25519", 256),
+            Ok(Algorithm::Rsa(ref modulus)) => ("RSA", significant_bits(modulus)),
+            Ok(Algorithm::Dsa(ref p_integer)) => ("DSA", significant_bits(p_integer)),
+            Ok(Algorithm::Ecdsa(ref curve, _)) => ("ECDSA", curve_bits(curve)),
+            Ok(Algorithm::SkEd25519(_, _)) => ("ED25519-SK", 256),
+            Ok(Algorithm::SkEcdsa(ref curve, _, _)) => ("ECDSA-SK", curve_bits(curve)),
+            _ => return None,
+        };
+        let digest = Sha256::digest(&self.public_blob);
+        Some(randomart::render(key_type, bits, "SHA256", &digest))
+    }
+
+    /// Encrypted with a cipher the format has a better choice than.
+    /// ssh-keygen has written aes256-ctr since OpenSSH 6.5, so any cbc cipher
+    /// in an openssh key is legacy. CBC is all OpenSSL offers for legacy PEM
+    /// and PKCS#8, where only the DES family is. PuTTY only offers aes256-cbc
+    /// (with a MAC), and ssh.com keys get a convert warning instead.
+    pub fn has_legacy_cipher(&self) -> bool {
+        let cipher = match self.cipher {
+            Some(ref cipher) => cipher.to_lowercase(),
+            None => return false,
+        };
+        match self.kdf {
+            Some(Kdf::Bcrypt(..)) => cipher.ends_with("-cbc"),
+            Some(Kdf::OpensslMd5) | Some(Kdf::Pbkdf2(..)) | Some(Kdf::Scrypt(..)) => {
+                cipher.starts_with("des")
+            }
+            _ => false,
+        }
+    }
+
+    /// Fewer bcrypt kdf rounds than `min_rounds`. Other kdfs have no
+    /// rounds setting, see `Kdf` for their own warnings.
+    pub fn has_weak_kdf(&self, min_rounds: u32) -> bool {
+        match self.kdf {
+            Some(Kdf::Bcrypt(_, rounds)) => rounds < min_rounds,
+            _ => false,
+        }
+    }
+
+    /// PKCS#8 encryption with PBES1 or too few PBKDF2 iterations
+    pub fn has_weak_pbe(&self) -> bool {
+        match self.kdf {
+            Some(Kdf::Pbkdf2(_, iterations, _)) => iterations < MIN_PBKDF2_ITERATIONS,
+            Some(Kdf::Pbes1(_)) => true,
+            _ => false,
         }
     }
 
@@ -62,15 +290,65 @@ impl SshKey {
                 Algorithm::Ecdsa(ref curve2, ref point2) => curve == curve2 && point == point2,
                 _ => false,
             },
+            Algorithm::SkEd25519(ref point, ref security_key) => match other.algorithm {
+                Algorithm::SkEd25519(ref point2, ref security_key2) => {
+                    point == point2 && security_key.application == security_key2.application
+                }
+                _ => false,
+            },
+            Algorithm::SkEcdsa(ref curve, ref point, ref security_key) => match other.algorithm {
+                Algorithm::SkEcdsa(ref curve2, ref point2, ref security_key2) => {
+                    curve == curve2
+                        && point == point2
+                        && security_key.application == security_key2.application
+                }
+                _ => false,
+            },
             _ => false,
         }
     }
 }
 
+/// Generic message for a failed nom parse
+pub fn parse_error<T>(result: nom::Err<T>) -> String {
+    match result {
+        nom::Err::Error(_) | nom::Err::Failure(_) => "Parse error".into(),
+        nom::Err::Incomplete(_) => "Didn't fully parse".into(),
+    }
+}
+
+/// Append an ssh wire-format string (big endian u32 length then the bytes).
+/// DER INTEGER contents are already valid ssh mpints, so this works for those too.
+pub fn push_string(blob: &mut Vec<u8>, bytes: &[u8]) {
+    let length = bytes.len() as u32;
+    blob.push((length >> 24) as u8);
+    blob.push((length >> 16) as u8);
+    blob.push((length >> 8) as u8);
+    blob.push(length as u8);
+    blob.extend_from_slice(bytes);
+}
+
 fn bit_count(field: &[u8]) -> usize {
     field.len() * 8
 }
 
+/// Like OpenSSL's BN_num_bits: ignore leading zero bits
+pub fn significant_bits(field: &[u8]) -> usize {
+    match field.iter().position(|&byte| byte != 0) {
+        Some(index) => (field.len() - index) * 8 - field[index].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+fn curve_bits(curve: &str) -> usize {
+    match curve {
+        "nistp256" => 256,
+        "nistp384" => 384,
+        "nistp521" => 521,
+        _ => 0,
+    }
+}
+
 impl fmt::Display for SshKey {
     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
         let mut output = String::new();
@@ -79,9 +357,12 @@ impl fmt::Display for SshKey {
         } else {
             output.push_str("private ");
         }
-        output.push_str("ssh key (");
+        match self.format {
+            Some(ref format) => output.push_str(&format!("key in {} format (", format)),
+            None => output.push_str("ssh key ("),
+        }
         output.push_str(&format!("{}", self.algorithm));
-        if !self.is_encrypted {
+        if !self.is_encrypted || self.is_decrypted {
             match self.algorithm {
                 Algorithm::Rsa(ref modulus) => {
                     output.push_str(&format!(", {} bits", bit_count(modulus)));
@@ -94,7 +375,9 @@ impl fmt::Display for SshKey {
         }
         if !self.is_public {
             output.push_str(", ");
-            if self.is_encrypted {
+            if self.is_decrypted {
+                output.push_str("encrypted, decrypted");
+            } else if self.is_encrypted {
                 output.push_str("encrypted");
             } else {
                 output.push_str("not encrypted");
@@ -143,6 +426,27 @@ named!(
     )
 );
 
+named!(
+    nom_sk_ed25519<(&[u8], &[u8])>,
+    do_parse!(
+        _key_type: length_bytes!(be_u32)
+            >> point: length_bytes!(be_u32)
+            >> application: length_bytes!(be_u32)
+            >> (point, application)
+    )
+);
+
+named!(
+    nom_sk_ecdsa<(&[u8], &[u8], &[u8])>,
+    do_parse!(
+        _key_type: length_bytes!(be_u32)
+            >> curve: length_bytes!(be_u32)
+            >> point: length_bytes!(be_u32)
+            >> application: length_bytes!(be_u32)
+            >> (curve, point, application)
+    )
+);
+
 pub fn peek_algorithm(is_encrypted: bool, key_bytes: &[u8]) -> Result<Algorithm, String> {
     if key_bytes.len() < 4 {
         return Err("Too short to be a valid ssh key".into());
@@ -168,6 +472,12 @@ pub fn peek_algorithm(is_encrypted: bool, key_bytes: &[u8]) -> Result<Algorithm,
     if algorithm_name.starts_with(b"ssh-rsa") {
         algorithm = Algorithm::Rsa(vec![]);
     }
+    if algorithm_name.starts_with(b"sk-ssh-ed25519@openssh.com") {
+        algorithm = Algorithm::SkEd25519(vec![], Default::default());
+    }
+    if algorithm_name.starts_with(b"sk-ecdsa-sha2-nistp256@openssh.com") {
+        algorithm = Algorithm::SkEcdsa("nistp256".into(), vec![], Default::default());
+    }
     if is_encrypted {
         return Ok(algorithm);
     }
@@ -202,60 +512,181 @@ pub fn peek_algorithm(is_encrypted: bool, key_bytes: &[u8]) -> Result<Algorithm,
             Err(nom::Err::Incomplete(_needed)) => Err("Didn't fully parse".into()),
         };
     }
+    if algorithm_name.starts_with(b"sk-ssh-ed25519@openssh.com") {
+        return match nom_sk_ed25519(key_bytes) {
+            Ok((_tail, (point, application))) => Ok(Algorithm::SkEd25519(
+                point.to_owned(),
+                SecurityKey::new(application),
+            )),
+            Err(nom::Err::Error(_e)) | Err(nom::Err::Failure(_e)) => Err("Parse error".into()),
+            Err(nom::Err::Incomplete(_needed)) => Err("Didn't fully parse".into()),
+        };
+    }
+    if algorithm_name.starts_with(b"sk-ecdsa-sha2-nistp256@openssh.com") {
+        return match nom_sk_ecdsa(key_bytes) {
+            Ok((_tail, (curve, point, application))) => Ok(Algorithm::SkEcdsa(
+                String::from_utf8_lossy(curve).into(),
+                point.to_owned(),
+                SecurityKey::new(application),
+            )),
+            Err(nom::Err::Error(_e)) | Err(nom::Err::Failure(_e)) => Err("Parse error".into()),
+            Err(nom::Err::Incomplete(_needed)) => Err("Didn't fully parse".into()),
+        };
+    }
     Ok(algorithm)
 }
 
 #[test]
 fn test_is_pair() {
-    let mut rsa_priv_1: SshKey = Default::default();
-    rsa_priv_1.algorithm = Algorithm::Rsa(vec![1, 2, 3]);
+    let rsa_priv_1 = SshKey {
+        algorithm: Algorithm::Rsa(vec![1, 2, 3]),
+        ..Default::default()
+    };
     assert!(!rsa_priv_1.is_pair(&rsa_priv_1));
 
-    let mut rsa_pub_1: SshKey = Default::default();
-    rsa_pub_1.is_public = true;
-    rsa_pub_1.algorithm = Algorithm::Rsa(vec![1, 2, 3]);
+    let rsa_pub_1 = SshKey {
+        algorithm: Algorithm::Rsa(vec![1, 2, 3]),
+        is_public: true,
+        ..Default::default()
+    };
     assert!(rsa_priv_1.is_pair(&rsa_pub_1));
     assert!(rsa_pub_1.is_pair(&rsa_priv_1));
 
-    let mut dsa_pub_1: SshKey = Default::default();
-    dsa_pub_1.is_public = true;
-    dsa_pub_1.algorithm = Algorithm::Dsa(vec![1, 2, 3]);
+    let dsa_pub_1 = SshKey {
+        algorithm: Algorithm::Dsa(vec![1, 2, 3]),
+        is_public: true,
+        ..Default::default()
+    };
     assert!(!dsa_pub_1.is_pair(&rsa_priv_1));
 
-    let mut rsa_pub_2: SshKey = Default::default();
-    rsa_pub_2.is_public = true;
-    rsa_pub_2.algorithm = Algorithm::Rsa(vec![4, 5, 6]);
+    let rsa_pub_2 = SshKey {
+        algorithm: Algorithm::Rsa(vec![4, 5, 6]),
+        is_public: true,
+        ..Default::default()
+    };
     assert!(!rsa_pub_2.is_pair(&rsa_priv_1));
     assert!(!rsa_priv_1.is_pair(&rsa_pub_2));
 
-    let mut dsa_priv_2: SshKey = Default::default();
-    dsa_priv_2.algorithm = Algorithm::Dsa(vec![4, 5, 6]);
+    let dsa_priv_2 = SshKey {
+        algorithm: Algorithm::Dsa(vec![4, 5, 6]),
+        ..Default::default()
+    };
     assert!(!dsa_priv_2.is_pair(&dsa_priv_2));
     assert!(!dsa_priv_2.is_pair(&rsa_pub_1));
     assert!(!dsa_priv_2.is_pair(&rsa_priv_1));
 
-    let mut dsa_pub_2: SshKey = Default::default();
-    dsa_pub_2.is_public = true;
-    dsa_pub_2.algorithm = Algorithm::Dsa(vec![4, 5, 6]);
+    let dsa_pub_2 = SshKey {
+        algorithm: Algorithm::Dsa(vec![4, 5, 6]),
+        is_public: true,
+        ..Default::default()
+    };
     assert!(dsa_pub_2.is_pair(&dsa_priv_2));
     assert!(dsa_priv_2.is_pair(&dsa_pub_2));
     assert!(!dsa_pub_2.is_pair(&rsa_priv_1));
 
-    let mut ecdsa_priv_1: SshKey = Default::default();
-    ecdsa_priv_1.algorithm = Algorithm::Ecdsa("nistp384".into(), vec![1, 2, 3]);
+    let ecdsa_priv_1 = SshKey {
+        algorithm: Algorithm::Ecdsa("nistp384".into(), vec![1, 2, 3]),
+        ..Default::default()
+    };
     assert!(!ecdsa_priv_1.is_pair(&rsa_priv_1));
-    let mut ecdsa_pub_1: SshKey = Default::default();
-    ecdsa_pub_1.is_public = true;
-    ecdsa_pub_1.algorithm = Algorithm::Ecdsa("nistp384".into(), vec![1, 2, 3]);
+    let ecdsa_pub_1 = SshKey {
+        algorithm: Algorithm::Ecdsa("nistp384".into(), vec![1, 2, 3]),
+        is_public: true,
+        ..Default::default()
+    };
     assert!(ecdsa_priv_1.is_pair(&ecdsa_pub_1));
     assert!(ecdsa_pub_1.is_pair(&ecdsa_priv_1));
 
-    let mut ed25519_priv_1: SshKey = Default::default();
-    ed25519_priv_1.algorithm = Algorithm::Ed25519(vec![7, 8, 9]);
+    let ed25519_priv_1 = SshKey {
+        algorithm: Algorithm::Ed25519(vec![7, 8, 9]),
+        ..Default::default()
+    };
     assert!(!ed25519_priv_1.is_pair(&rsa_priv_1));
-    let mut ed25519_pub_1: SshKey = Default::default();
-    ed25519_pub_1.is_public = true;
-    ed25519_pub_1.algorithm = Algorithm::Ed25519(vec![7, 8, 9]);
+    let ed25519_pub_1 = SshKey {
+        algorithm: Algorithm::Ed25519(vec![7, 8, 9]),
+        is_public: true,
+        ..Default::default()
+    };
     assert!(ed25519_priv_1.is_pair(&ed25519_pub_1));
     assert!(ed25519_pub_1.is_pair(&ed25519_priv_1));
+
+    let sk_priv_1 = SshKey {
+        algorithm: Algorithm::SkEd25519(vec![7, 8, 9], SecurityKey::new(b"ssh:")),
+        ..Default::default()
+    };
+    assert!(!sk_priv_1.is_pair(&ed25519_pub_1));
+    let sk_pub_1 = SshKey {
+        algorithm: Algorithm::SkEd25519(vec![7, 8, 9], SecurityKey::new(b"ssh:")),
+        is_public: true,
+        ..Default::default()
+    };
+    assert!(sk_priv_1.is_pair(&sk_pub_1));
+    assert!(sk_pub_1.is_pair(&sk_priv_1));
+    let sk_pub_2 = SshKey {
+        algorithm: Algorithm::SkEd25519(vec![7, 8, 9], SecurityKey::new(b"ssh:other")),
+        is_public: true,
+        ..Default::default()
+    };
+    assert!(!sk_priv_1.is_pair(&sk_pub_2));
+}
+
+#[test]
+fn test_security_key_flags() {
+    let mut security_key = SecurityKey::new(b"ssh:");
+    assert_eq!(security_key.user_presence_required(), None);
+    security_key.flags = Some(0x21);
+    assert_eq!(security_key.user_presence_required(), Some(true));
+    assert_eq!(security_key.user_verification_required(), Some(false));
+    assert_eq!(security_key.is_resident(), Some(true));
+}
+
+#[test]
+fn test_cipher_and_kdf_checks() {
+    let mut ssh_key: SshKey = Default::default();
+    assert!(!ssh_key.has_legacy_cipher());
+    assert!(!ssh_key.has_weak_kdf(16));
+    ssh_key.cipher = Some("aes256-ctr".into());
+    ssh_key.kdf = Some(Kdf::Bcrypt(16, 16));
+    assert!(!ssh_key.has_legacy_cipher());
+    assert!(!ssh_key.has_weak_kdf(16));
+    assert!(ssh_key.has_weak_kdf(100));
+    ssh_key.cipher = Some("aes128-cbc".into());
+    assert!(ssh_key.has_legacy_cipher());
+    ssh_key.cipher = Some("3des-cbc".into());
+    assert!(ssh_key.has_legacy_cipher());
+    ssh_key.cipher = Some("DES-EDE3-CBC".into());
+    ssh_key.kdf = Some(Kdf::OpensslMd5);
+    assert!(ssh_key.has_legacy_cipher());
+    assert!(!ssh_key.has_weak_kdf(1));
+    ssh_key.cipher = Some("AES-256-CBC".into());
+    assert!(!ssh_key.has_legacy_cipher());
+    ssh_key.cipher = Some("aes-256-cbc".into());
+    ssh_key.kdf = Some(Kdf::Pbkdf2("hmacWithSHA256".into(), 2048, 8));
+    assert!(!ssh_key.has_legacy_cipher());
+    assert!(!ssh_key.has_weak_kdf(16));
+    assert!(ssh_key.has_weak_pbe());
+    ssh_key.cipher = Some("des-ede3-cbc".into());
+    assert!(ssh_key.has_legacy_cipher());
+    ssh_key.kdf = Some(Kdf::Pbes1("pbeWithMD5AndDES-CBC".into()));
+    assert!(ssh_key.has_weak_pbe());
+    ssh_key.cipher = Some("aes256-cbc".into());
+    ssh_key.kdf = Some(Kdf::Argon2("Argon2id".into(), 8192, 21, 1));
+    assert!(!ssh_key.has_legacy_cipher());
+}
+
+#[test]
+fn test_fingerprints() {
+    let mut ssh_key: SshKey = Default::default();
+    assert_eq!(ssh_key.fingerprint_sha256(), None);
+    assert_eq!(ssh_key.fingerprint_md5(), None);
+    push_string(&mut ssh_key.public_blob, b"ssh-ed25519");
+    push_string(&mut ssh_key.public_blob, &[0u8; 32]);
+    assert_eq!(
+        ssh_key.fingerprint_sha256(),
+        Some("SHA256:kmYcvdi2GkPeWxB6XLjrZB8JHsy2Hm8luHMFp9GMvqk".into())
+    );
+    assert_eq!(
+        ssh_key.fingerprint_md5(),
+        Some("MD5:3d:52:38:ce:ff:94:f5:d7:81:a4:a4:f9:2b:4f:fe:a0".into())
+    );
 }
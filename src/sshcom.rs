@@ -0,0 +1,99 @@
+// ssh.com (Tectia) "---- BEGIN SSH2 ENCRYPTED PRIVATE KEY ----" files
+use asn1::mpint;
+use nom::be_u32;
+use public_key::{decode_rfc4716, dsa_public, rsa_public};
+use ssh_key::{parse_error, Algorithm, Kdf, SshKey};
+
+named!(
+    nom_sshcom_private<(&[u8], &[u8], &[u8])>,
+    do_parse!(
+        tag!(b"\x3f\x6f\xf9\xeb")
+            >> _length: be_u32
+            >> key_type: length_bytes!(be_u32)
+            >> cipher_name: length_bytes!(be_u32)
+            >> private: length_bytes!(be_u32)
+            >> (key_type, cipher_name, private)
+    )
+);
+
+// ssh.com integers are a bit count followed by just enough bytes
+named!(
+    nom_bits<&[u8]>,
+    do_parse!(bits: be_u32 >> value: take!((bits as usize).div_ceil(8)) >> (value))
+);
+
+// e, d, n, u, p, q
+named!(
+    nom_rsa<(&[u8], &[u8])>,
+    do_parse!(
+        e: nom_bits
+            >> _d: nom_bits
+            >> n: nom_bits
+            >> _u: nom_bits
+            >> _p: nom_bits
+            >> _q: nom_bits
+            >> (n, e)
+    )
+);
+
+// 0 (explicit parameters), p, g, q, y, x
+named!(
+    nom_dsa<(&[u8], &[u8], &[u8], &[u8])>,
+    do_parse!(
+        tag!(b"\0\0\0\0")
+            >> p: nom_bits
+            >> g: nom_bits
+            >> q: nom_bits
+            >> y: nom_bits
+            >> _x: nom_bits
+            >> (p, q, g, y)
+    )
+);
+
+named!(nom_payload<&[u8]>, length_bytes!(be_u32));
+
+// Only unencrypted keys have their parameters in the clear
+fn read_payload(key_type: &str, private: &[u8]) -> Result<(Algorithm, Vec<u8>), String> {
+    let (_tail, payload) = nom_payload(private).map_err(parse_error)?;
+    if key_type.starts_with("if-modn{sign{rsa") {
+        let (_tail, (n, e)) = nom_rsa(payload).map_err(parse_error)?;
+        Ok(rsa_public(&mpint(n), &mpint(e)))
+    } else if key_type.starts_with("dl-modp{sign{dsa") {
+        let (_tail, (p, q, g, y)) = nom_dsa(payload).map_err(parse_error)?;
+        Ok(dsa_public(&mpint(p), &mpint(q), &mpint(g), &mpint(y)))
+    } else {
+        Ok((Algorithm::Unknown, vec![]))
+    }
+}
+
+/// Parse an ssh.com private key. Despite the "ENCRYPTED" in its armor,
+/// the cipher may be "none", and then the key can be paired.
+pub fn parse(bytes: &[u8]) -> Result<SshKey, String> {
+    let (blob, comment) = decode_rfc4716(bytes, "SSH2 ENCRYPTED PRIVATE KEY")?;
+    let (_tail, (key_type, cipher_name, private)) =
+        nom_sshcom_private(&blob).map_err(parse_error)?;
+    let key_type = String::from_utf8_lossy(key_type);
+    let cipher_name = String::from_utf8_lossy(cipher_name);
+    let mut ssh_key = SshKey {
+        comment,
+        legacy_format: Some("ssh.com".into()),
+        ..Default::default()
+    };
+    if cipher_name == "none" {
+        let (algorithm, public_blob) = read_payload(&key_type, private)?;
+        ssh_key.algorithm = algorithm;
+        ssh_key.public_blob = public_blob;
+    } else {
+        ssh_key.is_encrypted = true;
+        ssh_key.cipher = Some(cipher_name.into_owned());
+        ssh_key.kdf = Some(Kdf::SshcomMd5);
+        ssh_key.algorithm = if key_type.starts_with("if-modn{sign{rsa") {
+            Algorithm::Rsa(vec![])
+        } else if key_type.starts_with("dl-modp{sign{dsa") {
+            Algorithm::Dsa(vec![])
+        } else {
+            Algorithm::Unknown
+        };
+    }
+    Ok(ssh_key)
+}
@@ -0,0 +1,183 @@
+use certificate::{self, BasicConstraints, Certificate, KeyUsage};
+use rsfs::{DirEntry, GenFS, Metadata};
+use std::io::Read;
+use std::path::Path;
+use time;
+
+pub const DEFAULT_TRUST_STORE: &str = "/etc/ssl/certs";
+
+/// The certificates a TLS client trusts as they are, usually roots
+#[derive(Debug, Default)]
+pub struct TrustStore {
+    pub certificates: Vec<Certificate>,
+}
+
+impl TrustStore {
+    // The same subject and key, whatever the serial or validity
+    fn find(&self, certificate: &Certificate) -> Option<&Certificate> {
+        self.certificates.iter().find(|trusted| {
+            trusted.subject == certificate.subject
+                && trusted.subject_public_key == certificate.subject_public_key
+        })
+    }
+
+    /// Walk `path`, a chain from the leaf up, to a trusted certificate.
+    /// Returns that certificate, or the first reason the chain fails.
+    pub fn verify<'a>(
+        &'a self,
+        path: &[&Certificate],
+        now: &time::Tm,
+    ) -> Result<&'a Certificate, String> {
+        for (depth, certificate) in path.iter().enumerate() {
+            check_validity(certificate, now)?;
+            let trusted = self.find(certificate);
+            if depth > 0 {
+                match trusted {
+                    Some(_) => check_anchor(certificate, depth - 1)?,
+                    None => check_issuer(certificate, depth - 1)?,
+                }
+            }
+            if let Some(trusted) = trusted {
+                return Ok(trusted);
+            }
+            match path.get(depth + 1) {
+                Some(issuer) => certificate.verify_signature(issuer)?,
+                None => return self.verify_anchor(certificate, depth, now),
+            }
+        }
+        Err("Empty chain".into())
+    }
+
+    // The top of a chain that isn't itself in the store
+    fn verify_anchor(
+        &self,
+        top: &Certificate,
+        depth: usize,
+        now: &time::Tm,
+    ) -> Result<&Certificate, String> {
+        if top.is_self_issued() {
+            return Err(format!("root {} isn't in the trust store", top.name()));
+        }
+        let mut first_error = None;
+        for anchor in self
+            .certificates
+            .iter()
+            .filter(|anchor| top.is_issued_by(anchor))
+        {
+            let result = check_validity(anchor, now)
+                .and_then(|_| check_anchor(anchor, depth))
+                .and_then(|_| top.verify_signature(anchor));
+            match result {
+                Ok(()) => return Ok(anchor),
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+        Err(first_error
+            .unwrap_or_else(|| format!("issuer {} isn't in the trust store", top.issuer)))
+    }
+}
+
+fn check_validity(certificate: &Certificate, now: &time::Tm) -> Result<(), String> {
+    if *now < certificate.not_before {
+        return Err(format!(
+            "{} isn't valid until {}",
+            certificate.name(),
+            certificate::format_date(&certificate.not_before)
+        ));
+    }
+    if *now > certificate.expires {
+        return Err(format!(
+            "{} expired on {}",
+            certificate.name(),
+            certificate::format_date(&certificate.expires)
+        ));
+    }
+    Ok(())
+}
+
+// `below` counts the intermediate CAs between this one and the leaf
+fn check_issuer(ca: &Certificate, below: usize) -> Result<(), String> {
+    match ca.basic_constraints {
+        Some(BasicConstraints {
+            is_ca: true,
+            path_len,
+        }) => {
+            if let Some(path_len) = path_len {
+                if (path_len as usize) < below {
+                    return Err(format!(
+                        "{} allows {} intermediate CAs below it, not {}",
+                        ca.name(),
+                        path_len,
+                        below
+                    ));
+                }
+            }
+        }
+        _ => return Err(format!("{} isn't a CA", ca.name())),
+    }
+    match ca.key_usage {
+        Some(ref usages) if !usages.contains(&KeyUsage::KeyCertSign) => {
+            Err(format!("{} has no keyCertSign key usage", ca.name()))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Old v1 roots have no extensions to check
+fn check_anchor(anchor: &Certificate, below: usize) -> Result<(), String> {
+    if anchor.version < 3 {
+        return Ok(());
+    }
+    check_issuer(anchor, below)
+}
+
+fn read<F: GenFS>(fs: &F, path: &Path) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    fs.open_file(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|error| format!("Error reading {}: {}", path.display(), error))?;
+    Ok(bytes)
+}
+
+/// Every certificate in a bundle file, or in the files of a directory like
+/// /etc/ssl/certs. Files in a directory that aren't certificates are skipped,
+/// and so are certificates already loaded from another file.
+pub fn load<F: GenFS>(fs: &F, path: &Path) -> Result<TrustStore, String> {
+    let metadata = fs
+        .metadata(path)
+        .map_err(|error| format!("Error reading {}: {}", path.display(), error))?;
+    let mut store: TrustStore = Default::default();
+    if !metadata.is_dir() {
+        store.certificates = certificate::parse_bundle(&read(fs, path)?)?.certificates;
+        return Ok(store);
+    }
+    let mut paths: Vec<_> = fs
+        .read_dir(path)
+        .map_err(|error| format!("Error reading {}: {}", path.display(), error))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        let bytes = match read(fs, &path) {
+            Ok(bytes) => bytes,
+            Err(_) => continue,
+        };
+        if !certificate::has_certificates(&bytes) {
+            continue;
+        }
+        if let Ok(bundle) = certificate::parse_bundle(&bytes) {
+            for certificate in bundle.certificates {
+                if store.find(&certificate).is_none() {
+                    store.certificates.push(certificate);
+                }
+            }
+        }
+    }
+    if store.certificates.is_empty() {
+        return Err(format!("No certificates in {}", path.display()));
+    }
+    Ok(store)
+}
@@ -0,0 +1,68 @@
+extern crate rsfs;
+extern crate tealeaves;
+use tealeaves::leaf::Leaf;
+use tealeaves::ssh_key::Algorithm;
+
+fn scan(path: &str) -> Leaf {
+    tealeaves::scan(&rsfs::disk::FS, &path).unwrap()
+}
+
+#[test]
+fn authorized_keys_entries() {
+    match scan("./files/authorized_keys") {
+        Leaf::AuthorizedKeys(_path, authorized_keys) => {
+            let lines: Vec<usize> = authorized_keys
+                .entries
+                .iter()
+                .map(|entry| entry.line)
+                .collect();
+            assert_eq!(lines, vec![2, 4, 5, 6, 7, 8]);
+            let backup = &authorized_keys.entries[1];
+            assert!(backup.has_option("restrict"));
+            assert_eq!(
+                backup.option_value("command"),
+                Some(r#"/usr/local/bin/backup --dir "/var/backups""#)
+            );
+            assert_eq!(backup.option_value("from"), Some("10.0.0.0/8,192.168.1.1"));
+            assert_eq!(backup.key.comment, Some("backup".into()));
+            match backup.key.algorithm {
+                Algorithm::Rsa(ref modulus) => assert_eq!(modulus.len() * 8, 2048),
+                _ => panic!("algorithm not detected correctly"),
+            }
+            let ca = &authorized_keys.entries[3];
+            assert!(ca.has_option("cert-authority"));
+            assert_eq!(ca.option_value("principals"), Some("alice,bob"));
+            assert_eq!(ca.option_value("expiry-time"), Some("20301231"));
+            let no_comment = &authorized_keys.entries[4];
+            assert!(no_comment.options.is_empty());
+            assert_eq!(no_comment.key.comment, None);
+            assert!(no_comment.is_unrestricted());
+            assert_eq!(authorized_keys.duplicates(), vec![(2, 8)]);
+            assert_eq!(
+                authorized_keys.errors,
+                vec![(9, "Options without a key".to_string())]
+            );
+        }
+        _ => panic!("Expected AuthorizedKeys"),
+    }
+}
+
+#[test]
+fn authorized_keys_warnings() {
+    let output = format!("{}", scan("./files/authorized_keys"));
+    assert!(output.contains("\n\t\t⚠️ dsa keys are considered insecure"));
+    assert!(output.contains("\n\t\t⚠️ RSA keys should be 2048 bits or larger"));
+    assert!(output.contains("\n\t\t⚠️ duplicate of the key on line 2"));
+    assert!(output.contains(r#"command="/usr/local/bin/backup --dir \"/var/backups\"""#));
+    // Not in a service account's home directory
+    assert!(!output.contains("service account"));
+
+    let output = format!("{}", scan("./files/service/git/.ssh/authorized_keys"));
+    assert_eq!(
+        output
+            .matches("unrestricted key for service account git")
+            .count(),
+        1
+    );
+    assert!(output.contains("line 2: public ssh key (ecdsa, curve nistp256)"));
+}
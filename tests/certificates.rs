@@ -0,0 +1,443 @@
+extern crate base64;
+extern crate rsfs;
+extern crate tealeaves;
+extern crate time;
+mod common;
+use rsfs::mem::unix::FS;
+use std::net::IpAddr;
+use std::path::Path;
+use tealeaves::certificate::{
+    BasicConstraints, Bundle, Certificate, Chain, GeneralName, KeyUsage, PssParameters, PublicKey,
+};
+use tealeaves::leaf::Leaf;
+use tealeaves::ssh_key::SshKey;
+use tealeaves::trust_store::{self, TrustStore};
+
+fn scan_key(path: &str) -> SshKey {
+    match tealeaves::scan(&rsfs::disk::FS, &path).unwrap() {
+        Leaf::SshKey(_path, key) => key,
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+fn scan_bundle(path: &str) -> Bundle {
+    match tealeaves::scan(&rsfs::disk::FS, &path).unwrap() {
+        Leaf::Certificates(_path, bundle) => bundle,
+        _ => panic!("Expected Certificates"),
+    }
+}
+
+fn scan(path: &str) -> Certificate {
+    match tealeaves::scan(&rsfs::disk::FS, &path).unwrap() {
+        Leaf::Certificate(_path, certificate) => certificate,
+        _ => panic!("Expected Certificate"),
+    }
+}
+
+#[test]
+fn x509_v1_certificate() {
+    let certificate = scan("./files/tls-x509.pem");
+    assert_eq!(certificate.version, 1);
+    assert_eq!(
+        certificate.serial_number(),
+        &[0xe4, 0x0d, 0x99, 0x2b, 0x67, 0xcd, 0xb9, 0x27]
+    );
+    assert_eq!(
+        certificate.subject.to_string(),
+        "C=US, ST=AL, L=Fairbanks, O=Tealeaves Development, OU=Tealeaves, CN=tealeaves.local"
+    );
+    assert!(certificate.is_self_issued());
+    assert_eq!(certificate.subject.common_name(), Some("tealeaves.local"));
+    assert_eq!(
+        (
+            certificate.not_before.tm_year + 1900,
+            certificate.not_before.tm_mon + 1,
+            certificate.not_before.tm_mday
+        ),
+        (2018, 1, 9)
+    );
+    assert!(certificate.is_expired());
+    assert_eq!(certificate.subject_alt_names, vec![]);
+    assert_eq!(certificate.key_usage, None);
+    assert_eq!(certificate.basic_constraints, None);
+    // Without SANs the CN is all there is
+    assert_eq!(certificate.host_names(), vec!["tealeaves.local"]);
+}
+
+#[test]
+fn x509_out_of_range_version_gets_error() {
+    let text = std::fs::read_to_string("./files/tls-x509-san.pem").unwrap();
+    let body: String = text
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let mut der = base64::decode(&body).unwrap();
+    // [0] EXPLICIT INTEGER 2, the encoded v3
+    let version = [0xa0, 0x03, 0x02, 0x01, 0x02];
+    let position = der
+        .windows(version.len())
+        .position(|window| window == version)
+        .unwrap();
+    der[position + 4] = 0xff;
+    assert_eq!(
+        tealeaves::certificate::parse_der(&der).err(),
+        Some("Invalid certificate version".into())
+    );
+}
+
+#[test]
+fn x509_v3_extensions() {
+    let certificate = scan("./files/tls-x509-san.pem");
+    assert_eq!(certificate.version, 3);
+    assert_eq!(
+        certificate.subject_alt_names,
+        vec![
+            GeneralName::Dns("tealeaves.local".into()),
+            GeneralName::Dns("*.tealeaves.local".into()),
+            GeneralName::Ip("127.0.0.1".parse::<IpAddr>().unwrap()),
+            GeneralName::Ip("::1".parse::<IpAddr>().unwrap()),
+            GeneralName::Email("admin@tealeaves.local".into()),
+            GeneralName::Uri("https://tealeaves.local/".into()),
+        ]
+    );
+    assert_eq!(
+        certificate.host_names(),
+        vec!["tealeaves.local", "*.tealeaves.local", "127.0.0.1", "::1"]
+    );
+    assert_eq!(
+        certificate.key_usage,
+        Some(vec![KeyUsage::DigitalSignature, KeyUsage::KeyAgreement])
+    );
+    assert_eq!(
+        certificate.extended_key_usage,
+        vec!["1.3.6.1.5.5.7.3.1", "1.3.6.1.5.5.7.3.2"]
+    );
+    assert_eq!(
+        certificate.basic_constraints,
+        Some(BasicConstraints {
+            is_ca: false,
+            path_len: None,
+        })
+    );
+    assert_eq!(
+        certificate.subject_key_id.as_ref().map(|id| id.len()),
+        Some(20)
+    );
+    assert_eq!(certificate.authority_key_id, certificate.subject_key_id);
+    // Past 2049 validity dates are GeneralizedTime
+    assert_eq!(certificate.expires.tm_year + 1900, 2126);
+}
+
+#[test]
+fn x509_display() {
+    let output = format!("{}", scan("./files/tls-x509-san.pem"));
+    assert!(output.starts_with(
+        "x509 TLS Certificate (hosts tealeaves.local, *.tealeaves.local, 127.0.0.1, ::1)"
+    ));
+    assert!(
+        output.contains("\n\talso names email:admin@tealeaves.local, URI:https://tealeaves.local/")
+    );
+    assert!(output.contains("\n\tkey usage: digitalSignature, keyAgreement"));
+    assert!(output.contains("\n\textended key usage: serverAuth, clientAuth"));
+    let output = format!("{}", scan("./files/tls-x509.pem"));
+    assert!(output.contains("\n\t⚠️ no subjectAltName, TLS clients ignore the CN"));
+    assert!(output.contains("\n\tx509 v1, serial e4:0d:99:2b:67:cd:b9:27, valid from 2018-01-09"));
+}
+
+#[test]
+fn x509_signature_algorithms() {
+    let certificate = scan("./files/tls-x509.pem");
+    assert_eq!(certificate.weak_signature_hash(), Some("sha1"));
+    let output = format!("{}", certificate);
+    assert!(output.contains("\n\t🚨 insecure SHA1 signature algorithm"));
+    assert!(output.contains("\n\tsigned with sha1WithRSAEncryption"));
+    // SHA-1 with ECDSA under an MD5 with RSA intermediate
+    let bundle = scan_bundle("./files/tls-chain-weak-hashes.pem");
+    let hashes: Vec<Option<&str>> = bundle
+        .certificates
+        .iter()
+        .map(|certificate| certificate.weak_signature_hash())
+        .collect();
+    assert_eq!(hashes, vec![Some("sha1"), Some("md5")]);
+    assert!(format!("{}", bundle.certificates[0]).contains("\n\tsigned with ecdsa-with-SHA1"));
+    let output = format!(
+        "{}",
+        tealeaves::scan(&rsfs::disk::FS, &"./files/tls-chain-weak-hashes.pem").unwrap()
+    );
+    assert!(output.contains("\n\t#1 sha1.tealeaves.local, expires 2126-09-24, 🚨 insecure SHA1"));
+    assert!(output.contains("(intermediate CA), expires 2126-09-24, 🚨 insecure MD5 signature"));
+    // RSA-PSS keeps its hash in the parameters
+    let bundle = scan_bundle("./files/tls-chain-algorithms.pem");
+    let pss = &bundle.certificates[0];
+    assert_eq!(
+        pss.pss,
+        Some(PssParameters {
+            hash: "sha256".into(),
+            mask_generation: "mgf1".into(),
+            mask_generation_hash: "sha256".into(),
+            salt_length: 32,
+        })
+    );
+    assert_eq!(pss.weak_signature_hash(), None);
+    assert!(format!("{}", pss)
+        .contains("\n\tsigned with rsassaPss (sha256, mgf1 with sha256, salt length 32)"));
+    assert_eq!(bundle.certificates[1].pss, None);
+    assert_eq!(bundle.certificates[1].signature_hash(), Some("sha384"));
+}
+
+#[test]
+fn x509_public_key() {
+    let certificate = scan("./files/tls-x509.pem");
+    assert_eq!(certificate.public_key, PublicKey::Rsa(2048, 65537));
+    assert_eq!(certificate.public_key.warnings(), Vec::<String>::new());
+    let certificate = scan("./files/tls-x509-san.pem");
+    assert_eq!(
+        certificate.public_key,
+        PublicKey::Ec("1.2.840.10045.3.1.7".into())
+    );
+    let certificate = scan("./files/tls-x509-rsa-1024.pem");
+    assert_eq!(certificate.public_key, PublicKey::Rsa(1024, 65537));
+    assert!(format!("{}", certificate).contains("\n\t🚨 RSA keys should be 2048 bits or larger"));
+    assert_eq!(
+        PublicKey::Ec("1.2.840.10045.3.1.1".into()).warnings(),
+        vec!["🚨 weak curve P-192"]
+    );
+    assert_eq!(
+        PublicKey::Ec("1.3.132.0.10".into()).warnings(),
+        vec!["⚠️ curve secp256k1 isn't supported by most TLS clients"]
+    );
+}
+
+#[test]
+fn x509_pairs_with_private_key() {
+    let rsa = scan_key("./files/tls-rsa-2048-private-key.pem");
+    let ecdsa = scan_key("./files/tls-ecdsa-256-private-key.pem");
+    let certificate = scan("./files/tls-x509.pem");
+    assert!(certificate.pairs_with(&rsa));
+    assert!(!certificate.pairs_with(&ecdsa));
+    let certificate = scan("./files/tls-x509-san.pem");
+    assert!(certificate.pairs_with(&ecdsa));
+    assert!(!certificate.pairs_with(&rsa));
+    // Public keys hold no private half
+    assert!(!certificate.pairs_with(&certificate.key));
+}
+
+#[test]
+fn x509_fullchain() {
+    let bundle = scan_bundle("./files/tls-fullchain.pem");
+    let names: Vec<String> = bundle.certificates.iter().map(|cert| cert.name()).collect();
+    assert_eq!(
+        names,
+        vec!["www.tealeaves.local", "Tealeaves Intermediate CA"]
+    );
+    assert!(bundle.certificates[0].is_issued_by(&bundle.certificates[1]));
+    let chains = bundle.chains();
+    assert_eq!(chains.len(), 1);
+    assert_eq!(chains[0].indexes, vec![0, 1]);
+    assert!(chains[0].is_in_order());
+    assert_eq!(
+        chains[0]
+            .missing_issuer
+            .as_ref()
+            .map(|issuer| issuer.to_string()),
+        Some("C=US, O=Tealeaves Development, CN=Tealeaves Root CA".into())
+    );
+    assert_eq!(bundle.duplicates(), vec![]);
+}
+
+// tls-fullchain.pem with a corrupt CERTIFICATE block between its two certificates
+fn fullchain_with_bad_block() -> Vec<u8> {
+    let text = std::fs::read_to_string("./files/tls-fullchain.pem").unwrap();
+    let end = "-----END CERTIFICATE-----\n";
+    let split = text.find(end).unwrap() + end.len();
+    let bad = "-----BEGIN CERTIFICATE-----\nMAA=\n-----END CERTIFICATE-----\n";
+    [&text[..split], bad, &text[split..]].concat().into_bytes()
+}
+
+#[test]
+fn x509_bundle_keeps_certificates_around_a_bad_block() {
+    let leaf = common::scan_bytes("fullchain.pem", &fullchain_with_bad_block()).unwrap();
+    assert!(format!("{}", leaf).contains("\n\t🚨 CERTIFICATE block 2 skipped: "));
+    let bundle = match leaf {
+        Leaf::Certificates(_path, bundle) => bundle,
+        _ => panic!("Expected Certificates"),
+    };
+    assert_eq!(bundle.certificates.len(), 2);
+    assert_eq!(bundle.errors.len(), 1);
+    assert_eq!(bundle.errors[0].0, 2);
+    assert_eq!(bundle.chains()[0].indexes, vec![0, 1]);
+}
+
+#[test]
+fn x509_bundle_order_and_duplicates() {
+    // Over 4096 bytes, with comments between the blocks
+    let bundle = scan_bundle("./files/tls-chain-bundle.pem");
+    assert_eq!(bundle.certificates.len(), 4);
+    assert_eq!(bundle.duplicates(), vec![(1, 3)]);
+    assert_eq!(
+        bundle.chains(),
+        vec![Chain {
+            indexes: vec![1, 2, 0],
+            missing_issuer: None,
+        }]
+    );
+    assert!(!bundle.chains()[0].is_in_order());
+    let output = format!(
+        "{}",
+        tealeaves::scan(&rsfs::disk::FS, &"./files/tls-chain-bundle.pem").unwrap()
+    );
+    assert!(output.contains("\n\t#1 Tealeaves Root CA (root CA), expires 2126-"));
+    assert!(output.contains(
+        "\n\t⚠️ chain #2 → #3 → #1 is out of order, each certificate should come before its issuer"
+    ));
+    assert!(output.contains("\n\t⚠️ #4 is a duplicate of #2"));
+}
+
+#[test]
+fn x509_bundle_gap() {
+    let bundle = scan_bundle("./files/tls-chain-gap.pem");
+    let chains = bundle.chains();
+    assert_eq!(chains.len(), 2);
+    assert_eq!(chains[0].indexes, vec![0]);
+    assert_eq!(
+        chains[0]
+            .missing_issuer
+            .as_ref()
+            .and_then(|issuer| issuer.common_name()),
+        Some("Tealeaves Intermediate CA")
+    );
+    // The root is a chain of its own
+    assert_eq!(
+        chains[1],
+        Chain {
+            indexes: vec![1],
+            missing_issuer: None,
+        }
+    );
+    let output = format!(
+        "{}",
+        tealeaves::scan(&rsfs::disk::FS, &"./files/tls-chain-gap.pem").unwrap()
+    );
+    assert!(output.contains(
+        "\n\t🚨 chain #1 is missing the intermediate C=US, O=Tealeaves Development, CN=Tealeaves Intermediate CA"
+    ));
+}
+
+fn store(path: &str) -> TrustStore {
+    trust_store::load(&rsfs::disk::FS, Path::new(path)).unwrap()
+}
+
+fn now() -> time::Tm {
+    time::strptime("2030-01-01", "%Y-%m-%d").unwrap()
+}
+
+// The first failure, or the trusted CN, of each chain in a bundle
+fn verify(bundle: &Bundle, store: &TrustStore) -> Vec<Result<String, String>> {
+    bundle
+        .chains()
+        .iter()
+        .map(|chain| {
+            let path: Vec<&Certificate> = chain
+                .indexes
+                .iter()
+                .map(|&index| &bundle.certificates[index])
+                .collect();
+            store.verify(&path, &now()).map(|anchor| anchor.name())
+        })
+        .collect()
+}
+
+#[test]
+fn x509_verify_signatures() {
+    let bundle = scan_bundle("./files/tls-fullchain.pem");
+    let (leaf, intermediate) = (&bundle.certificates[0], &bundle.certificates[1]);
+    // ECDSA P-256 leaf, RSA PKCS#1 v1.5 signature
+    assert_eq!(leaf.verify_signature(intermediate), Ok(()));
+    assert_eq!(
+        intermediate.verify_signature(leaf),
+        Err(
+            "unsupported signature algorithm sha256WithRSAEncryption with the issuer's EC curve \
+             P-256 key"
+                .into()
+        )
+    );
+    let root = &store("./files/tls-root-ca.pem").certificates[0];
+    assert_eq!(intermediate.verify_signature(root), Ok(()));
+    // RSA-PSS, ECDSA with SHA-384 and Ed25519
+    let bundle = scan_bundle("./files/tls-chain-algorithms.pem");
+    assert_eq!(
+        verify(&bundle, &store("./files/tls-root-ca-ed25519.pem")),
+        vec![Ok("Tealeaves Ed25519 Root CA".into())]
+    );
+    assert_eq!(
+        verify(&bundle, &store("./files/tls-root-ca.pem")),
+        vec![Err(
+            "issuer C=US, O=Tealeaves Development, CN=Tealeaves Ed25519 Root CA \
+             isn't in the trust store"
+                .into()
+        )]
+    );
+}
+
+#[test]
+fn x509_verify_failures() {
+    let store = store("./files/tls-root-ca.pem");
+    assert_eq!(
+        verify(&scan_bundle("./files/tls-chain-failures.pem"), &store),
+        vec![
+            Err("expired.tealeaves.local expired on 2020-01-01".into()),
+            Err("Tealeaves Intermediate CA allows 0 intermediate CAs below it, not 1".into()),
+            Err("Tealeaves Signing Only CA has no keyCertSign key usage".into()),
+            Err("signature doesn't verify with the key of Tealeaves Intermediate CA".into()),
+        ]
+    );
+    // The root is in the bundle as well as the store
+    assert_eq!(
+        verify(&scan_bundle("./files/tls-chain-bundle.pem"), &store),
+        vec![Ok("Tealeaves Root CA".into())]
+    );
+    let leaf = tealeaves::scan(&rsfs::disk::FS, &"./files/tls-x509.pem").unwrap();
+    assert_eq!(
+        leaf.trust_findings(&store, &now()),
+        vec!["🚨 doesn't verify: tealeaves.local expired on 2023-01-08"]
+    );
+    let leaf = tealeaves::scan(&rsfs::disk::FS, &"./files/tls-fullchain.pem").unwrap();
+    assert_eq!(
+        leaf.trust_findings(&store, &now()),
+        vec!["✓ chain #1 → #2 verifies to trusted Tealeaves Root CA"]
+    );
+}
+
+#[test]
+fn trust_store_directory() {
+    let fs = FS::new();
+    for (name, fixture) in &[
+        ("ca-certificates.crt", "./files/tls-chain-bundle.pem"),
+        ("tealeaves.pem", "./files/tls-root-ca.pem"),
+        ("ed25519.pem", "./files/tls-root-ca-ed25519.pem"),
+        ("README", "./files/ssh_config"),
+    ] {
+        common::copy_fixture(&fs, fixture, Path::new("/etc/ssl/certs").join(name));
+    }
+    let store = trust_store::load(&fs, Path::new("/etc/ssl/certs")).unwrap();
+    // The root and the leaf's duplicate are only loaded once
+    let names: Vec<String> = store.certificates.iter().map(|cert| cert.name()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Tealeaves Root CA",
+            "www.tealeaves.local",
+            "Tealeaves Intermediate CA",
+            "Tealeaves Ed25519 Root CA",
+        ]
+    );
+    assert!(trust_store::load(&fs, Path::new("/etc/ssl/missing")).is_err());
+}
+
+#[test]
+fn trust_store_bundle_with_a_bad_block() {
+    let fs = FS::new();
+    common::write(&fs, "/etc/ssl/cert.pem", &fullchain_with_bad_block());
+    let store = trust_store::load(&fs, Path::new("/etc/ssl/cert.pem")).unwrap();
+    assert_eq!(store.certificates.len(), 2);
+}
@@ -0,0 +1,49 @@
+// Helpers shared by the integration tests, each of which uses only some
+#![allow(dead_code)]
+use rsfs::mem::unix::{Permissions, FS};
+use rsfs::unix_ext::PermissionsExt;
+use rsfs::GenFS;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tealeaves::leaf::Leaf;
+
+/// An in-memory filesystem with an empty /tmp
+pub fn memfs() -> FS {
+    let fs = FS::new();
+    fs.create_dir_all("/tmp").unwrap();
+    fs
+}
+
+/// Write `contents` to `path`, readable only by its owner as ssh wants
+pub fn write<P: AsRef<Path>>(fs: &FS, path: P, contents: &[u8]) {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs.create_dir_all(parent).unwrap();
+    }
+    let mut file = fs.create_file(path).unwrap();
+    file.write_all(contents).unwrap();
+    fs.set_permissions(path, Permissions::from_mode(0o600))
+        .unwrap();
+}
+
+pub fn copy_fixture<P: AsRef<Path>>(fs: &FS, fixture: &str, path: P) {
+    write(fs, path, &fs::read(fixture).unwrap());
+}
+
+/// Scan `contents` as the file /tmp/`name` of a fresh in-memory filesystem
+pub fn scan_bytes(name: &str, contents: &[u8]) -> Result<Leaf, String> {
+    scan_bytes_with_passphrase(name, contents, None)
+}
+
+/// Like `scan_bytes`, decrypting private keys with `passphrase`
+pub fn scan_bytes_with_passphrase(
+    name: &str,
+    contents: &[u8],
+    passphrase: Option<&[u8]>,
+) -> Result<Leaf, String> {
+    let fs = memfs();
+    let path = Path::new("/tmp").join(name);
+    write(&fs, &path, contents);
+    tealeaves::scan_with_passphrase(&fs, &path, passphrase)
+}
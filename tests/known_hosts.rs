@@ -0,0 +1,201 @@
+extern crate rsfs;
+extern crate tealeaves;
+mod common;
+use rsfs::mem::unix::FS;
+use rsfs::unix_ext::PermissionsExt;
+use rsfs::{GenFS, Metadata};
+use std::fs;
+use std::io::Read;
+use tealeaves::known_hosts::{self, Hosts, KnownHosts, Maintenance, Marker};
+use tealeaves::leaf::Leaf;
+
+fn scan(path: &str) -> KnownHosts {
+    match tealeaves::scan(&rsfs::disk::FS, &path).unwrap() {
+        Leaf::KnownHosts(_path, known_hosts) => known_hosts,
+        _ => panic!("Expected KnownHosts"),
+    }
+}
+
+fn lookup_lines(known_hosts: &KnownHosts, host: &str, port: u16) -> Vec<usize> {
+    known_hosts
+        .lookup(host, port)
+        .iter()
+        .map(|entry| entry.line)
+        .collect()
+}
+
+#[test]
+fn known_hosts_entries() {
+    let known_hosts = scan("./files/known_hosts");
+    assert_eq!(known_hosts.entries.len(), 10);
+    assert_eq!(
+        known_hosts.entries[0].hosts,
+        Hosts::Patterns(vec!["github.com".into(), "140.82.112.3".into()])
+    );
+    assert_eq!(known_hosts.entries[6].marker, Some(Marker::CertAuthority));
+    assert_eq!(
+        known_hosts.entries[6].key.comment,
+        Some("example CA".into())
+    );
+    assert_eq!(known_hosts.entries[7].marker, Some(Marker::Revoked));
+    match known_hosts.entries[8].hosts {
+        Hosts::Hashed(ref salt, ref hash) => {
+            assert_eq!(salt.len(), 20);
+            assert_eq!(hash.len(), 20);
+        }
+        _ => panic!("Expected a hashed host"),
+    }
+    assert_eq!(
+        known_hosts.key_type_counts(),
+        vec![
+            ("ssh-ed25519".into(), 6),
+            ("ssh-rsa".into(), 2),
+            ("ssh-dss".into(), 1),
+            ("ecdsa-sha2-nistp256".into(), 1),
+        ]
+    );
+    let weak: Vec<usize> = known_hosts
+        .entries
+        .iter()
+        .filter(|entry| entry.is_weak())
+        .map(|entry| entry.line)
+        .collect();
+    assert_eq!(weak, vec![4, 5]);
+    assert_eq!(
+        known_hosts.conflicts(),
+        vec![(
+            "conflict.example.com".into(),
+            "ssh-ed25519".into(),
+            vec![6, 7]
+        )]
+    );
+    assert_eq!(known_hosts.errors, vec![(12, "Missing key".to_string())]);
+}
+
+#[test]
+fn known_hosts_lookup() {
+    let known_hosts = scan("./files/known_hosts");
+    assert_eq!(lookup_lines(&known_hosts, "github.com", 22), vec![2]);
+    assert_eq!(lookup_lines(&known_hosts, "140.82.112.3", 22), vec![2]);
+    assert_eq!(
+        lookup_lines(&known_hosts, "github.com", 2222),
+        Vec::<usize>::new()
+    );
+    assert_eq!(lookup_lines(&known_hosts, "git.example.com", 2222), vec![3]);
+    // The @cert-authority wildcard, except for its negated host
+    assert_eq!(lookup_lines(&known_hosts, "www.example.com", 22), vec![8]);
+    assert_eq!(
+        lookup_lines(&known_hosts, "untrusted.example.com", 22),
+        Vec::<usize>::new()
+    );
+    assert_eq!(
+        lookup_lines(&known_hosts, "revoked.example.com", 22),
+        vec![8, 9]
+    );
+    // Hashed with ssh-keygen -H
+    assert_eq!(
+        lookup_lines(&known_hosts, "hashed.example.com", 22),
+        vec![8, 10]
+    );
+    assert_eq!(
+        lookup_lines(&known_hosts, "hashed.example.com", 2222),
+        vec![11]
+    );
+    assert_eq!(
+        lookup_lines(&known_hosts, "unknown.example.org", 22),
+        Vec::<usize>::new()
+    );
+}
+
+#[test]
+fn known_hosts_display() {
+    let leaf = tealeaves::scan(&rsfs::disk::FS, &"./files/known_hosts").unwrap();
+    let output = format!("{}", leaf);
+    assert!(output.contains("\n\t✓ known_hosts with 10 entries"));
+    assert!(output.contains("\n\tline 4: ⚠️ weak ssh-dss host key for old.example.com"));
+    assert!(output.contains("\n\tline 9: @revoked ssh-ed25519 key for revoked.example.com"));
+    assert!(output.contains("conflicting ssh-ed25519 keys for conflict.example.com on lines 6, 7"));
+}
+
+fn memfs_known_hosts(contents: &[u8]) -> FS {
+    let fs = FS::new();
+    common::write(&fs, "/home/a/.ssh/known_hosts", contents);
+    fs
+}
+
+fn read(fs: &FS, path: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    fs.open_file(path).unwrap().read_to_end(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn known_hosts_maintain() {
+    let original = fs::read("./files/known_hosts").unwrap();
+    let fs = memfs_known_hosts(&original);
+    let path = std::path::Path::new("/home/a/.ssh/known_hosts");
+    let report = known_hosts::maintain(&fs, path, &Default::default()).unwrap();
+    // github.com and its address, git.example.com, both conflict.example.com
+    // keys and the first hashed.example.com; the ssh-dss line is dropped
+    assert_eq!(report.hashed, 7);
+    assert_eq!(report.merged, 0);
+    assert_eq!(report.dropped, 1);
+    assert_eq!(read(&fs, "/home/a/.ssh/known_hosts.old"), original);
+    assert!(fs.metadata("/home/a/.ssh/known_hosts.tmp").is_err());
+    assert_eq!(
+        fs.metadata(path).unwrap().permissions().mode() & 0o777,
+        0o600
+    );
+
+    let rewritten = known_hosts::parse(&read(&fs, "/home/a/.ssh/known_hosts"));
+    assert!(rewritten
+        .entries
+        .iter()
+        .all(|entry| entry.key_type != "ssh-dss"));
+    for &(host, port) in &[
+        ("github.com", 22),
+        ("140.82.112.3", 22),
+        ("git.example.com", 2222),
+    ] {
+        let found = rewritten.lookup(host, port);
+        assert_eq!(found.len(), 1);
+        match found[0].hosts {
+            Hosts::Hashed(_, _) => (),
+            _ => panic!("Expected {} to be hashed", host),
+        }
+    }
+    // Markers and wildcards stay readable, invalid lines are kept as is
+    assert_eq!(lookup_lines(&rewritten, "revoked.example.com", 22).len(), 2);
+    assert_eq!(rewritten.errors.len(), 1);
+
+    // Running again finds nothing to do and keeps the backup
+    let report = known_hosts::maintain(&fs, path, &Default::default()).unwrap();
+    assert!(report.is_unchanged());
+    assert_eq!(read(&fs, "/home/a/.ssh/known_hosts.old"), original);
+}
+
+#[test]
+fn known_hosts_rewrite_merge_and_prune() {
+    let ed25519 =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKYflJLTsIfmlVCowFZuYhFJorEr5527DsX+0P6kzB7G";
+    let dss = "ssh-dss AAAAB3NzaC1kc3MAAAABAQAAAAEBAAAAAQEAAAABAQ==";
+    let contents = format!(
+        "a.example.com {0}\nb.example.com,a.example.com {0}\n@revoked c.example.com {1}\nd.example.com {1}\n",
+        ed25519, dss
+    );
+    let maintenance = Maintenance {
+        hash: false,
+        ..Default::default()
+    };
+    let (output, report) = known_hosts::rewrite(contents.as_bytes(), &maintenance).unwrap();
+    assert_eq!(report.merged, 1);
+    assert_eq!(report.dropped, 1);
+    assert_eq!(report.hashed, 0);
+    assert_eq!(
+        output,
+        format!(
+            "a.example.com,b.example.com {0}\n@revoked c.example.com {1}\n",
+            ed25519, dss
+        )
+    );
+}
@@ -1,7 +1,8 @@
 extern crate rsfs;
 extern crate tealeaves;
+use std::fs;
 use tealeaves::leaf::Leaf;
-use tealeaves::ssh_key::Algorithm;
+use tealeaves::ssh_key::{Algorithm, Kdf};
 
 fn test_vec(length: usize) -> Vec<u8> {
     let mut modulus = vec![];
@@ -13,6 +14,10 @@ fn scan(path: &str) -> Leaf {
     tealeaves::scan(&rsfs::disk::FS, &path).unwrap()
 }
 
+fn decrypt(path: &str, passphrase: &str) -> Result<Leaf, String> {
+    tealeaves::scan_with_passphrase(&rsfs::disk::FS, &path, Some(passphrase.as_bytes()))
+}
+
 #[test]
 fn rsa_1024_private_passphrase_scan() {
     match scan("./files/ssh-rsa-2048-b-private-key-passphrase.pem") {
@@ -84,7 +89,11 @@ fn ed25519_private_clear() {
             };
             assert_eq!(ssh_key.is_public, false);
             assert_eq!(ssh_key.is_encrypted, false);
-            assert_eq!(ssh_key.comment, None);
+            assert_eq!(
+                ssh_key.comment,
+                Some("Tealeves test ED25519 SSH Key 1".into())
+            );
+            assert!(ssh_key.errors.is_empty());
         }
         _ => panic!("Expected SshKey"),
     }
@@ -151,3 +160,641 @@ fn rsa_4096_private_clear() {
         _ => panic!("Expected SshKey"),
     }
 }
+
+#[test]
+fn ed25519_sk_private_stub() {
+    match scan("./files/ssh-ed25519-sk-a-private-key.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            match ssh_key.algorithm {
+                Algorithm::SkEd25519(ref point, ref security_key) => {
+                    assert_eq!(point.len(), 32);
+                    assert_eq!(security_key.application, "ssh:");
+                    assert_eq!(security_key.user_presence_required(), Some(true));
+                    assert_eq!(security_key.user_verification_required(), Some(false));
+                    assert_eq!(security_key.is_resident(), Some(true));
+                    assert_eq!(security_key.key_handle_length, Some(64));
+                }
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(ssh_key.is_public, false);
+            assert_eq!(ssh_key.is_encrypted, false);
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn ecdsa_sk_private_stub() {
+    match scan("./files/ssh-ecdsa-sk-256-a-private-key.pem") {
+        Leaf::SshKey(path, ssh_key) => {
+            match ssh_key.algorithm {
+                Algorithm::SkEcdsa(ref curve, ref point, ref security_key) => {
+                    assert_eq!(curve, "nistp256");
+                    assert_eq!(point.len(), 65);
+                    assert_eq!(security_key.user_presence_required(), Some(true));
+                    assert_eq!(security_key.user_verification_required(), Some(true));
+                    assert_eq!(security_key.is_resident(), Some(false));
+                    assert_eq!(security_key.key_handle_length, Some(96));
+                }
+                _ => panic!("algorithm not detected correctly"),
+            };
+            match scan("./files/ssh-ecdsa-sk-256-a-public-key") {
+                Leaf::SshKey(_path, public_key) => assert!(ssh_key.is_pair(&public_key)),
+                _ => panic!("Expected SshKey"),
+            }
+            let output = format!("{}", Leaf::SshKey(path, ssh_key));
+            assert!(output.contains("✓ requires touching the security key"));
+            assert!(output.contains("✓ requires PIN or biometric verification"));
+            assert!(output.contains("key handle is 96 bytes"));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn fingerprints_match_ssh_keygen() {
+    let fixtures = [
+        (
+            "./files/ssh-dsa-1024-a-private-key.pem",
+            "SHA256:bH3vQYE2ewtSf1vjxb0t4sc5ER9cv8toO87u6KOJWb4",
+        ),
+        (
+            "./files/ssh-dsa-1024-c-private-key.pem",
+            "SHA256:PqIxjC94arbbWBXHF5dypVzGrgQD1bedAyD1c7RLCl8",
+        ),
+        (
+            "./files/ssh-dsa-1024-d-private-passphrase",
+            "SHA256:FNIYXCMEDleuAVEeFvh7JTE2QvEgdMbWiuS1Fw4nPbg",
+        ),
+        (
+            "./files/ssh-ecdsa-256-a-private-key.pem",
+            "SHA256:aztC83ECiE/mEH73NDZlXwzeiow/OCSbyKv8LHS6TyM",
+        ),
+        (
+            "./files/ssh-ecdsa-384-b-private-key.pem",
+            "SHA256:rMWzZtlbshDG3/qSPm7XbN2dZvwPoHOFb0X4BS8M0Uc",
+        ),
+        (
+            "./files/ssh-ecdsa-521-a-private-key.pem",
+            "SHA256:wxKqHU2+QkhrucBovm3NIL2JRzx0L7wPVgPSoT4AODg",
+        ),
+        (
+            "./files/ssh-ed25519-a-private-key.pem",
+            "SHA256:VK3YHtc+HoYqK+e3/UBrrLV7//T6zpd5bza7ITP/kEw",
+        ),
+        (
+            "./files/ssh-ed25519-sk-a-private-key.pem",
+            "SHA256:EDgbijjQ+E3Wf/F8OgpINtab5ITFHDGdAhMvyuNUvqU",
+        ),
+        (
+            "./files/ssh-ecdsa-sk-256-a-private-key.pem",
+            "SHA256:UdA4JAoFDFVF54NNvPioUU3o6MAgdTRne/STnfDbJ9k",
+        ),
+        (
+            "./files/ssh-ed25519-b-private-key-passphrase.pem",
+            "SHA256:yw0Y7EN4xO/fr/3D0QU/Wfol/by3v7618X8XlIuYg2E",
+        ),
+        (
+            "./files/ssh-rsa-1024-b-private-key.pem",
+            "SHA256:zp3/Yja6STdWLDtuqnepn9ivABwwk/2Rko9REKjsPJA",
+        ),
+        (
+            "./files/ssh-rsa-4096-a-private-key.pem",
+            "SHA256:NMGYc2WXxvOp6OiWTytd52n5/jCsRgKbzZr6EcecagY",
+        ),
+    ];
+    for &(path, sha256) in &fixtures {
+        match scan(path) {
+            Leaf::SshKey(_path, ssh_key) => {
+                assert_eq!(ssh_key.fingerprint_sha256(), Some(sha256.into()));
+            }
+            _ => panic!("Expected SshKey"),
+        }
+    }
+}
+
+#[test]
+fn fingerprint_unavailable_for_encrypted_pem() {
+    match scan("./files/ssh-rsa-2048-b-private-key-passphrase.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert_eq!(ssh_key.fingerprint_sha256(), None);
+            assert_eq!(ssh_key.fingerprint_md5(), None);
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn multi_key_openssh_container() {
+    let leaf = scan("./files/ssh-multi-a-private-key.pem");
+    match leaf {
+        Leaf::SshKeys(_, ref keys) => {
+            assert_eq!(keys.len(), 2);
+            match keys[0].algorithm {
+                Algorithm::Ed25519(ref point) => assert_eq!(point.len(), 32),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            match keys[1].algorithm {
+                Algorithm::Ecdsa(ref curve, _) => assert_eq!(curve, "nistp384"),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(
+                keys[1].fingerprint_sha256(),
+                Some("SHA256:rMWzZtlbshDG3/qSPm7XbN2dZvwPoHOFb0X4BS8M0Uc".into())
+            );
+            assert_eq!(keys[1].comment, Some("plyons@avi".into()));
+            assert!(keys.iter().all(|key| key.errors.is_empty()));
+        }
+        _ => panic!("Expected SshKeys"),
+    }
+    let leaves = leaf.flatten();
+    assert_eq!(leaves.len(), 2);
+    match (&leaves[0], scan("./files/ssh-ed25519-a-public-key")) {
+        (Leaf::SshKey(_, private_key), Leaf::SshKey(_, public_key)) => {
+            assert!(private_key.is_pair(&public_key));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn openssh_cipher_and_kdf_details() {
+    match scan("./files/ssh-ed25519-b-private-key-passphrase.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert_eq!(ssh_key.cipher, Some("aes256-cbc".into()));
+            assert_eq!(ssh_key.kdf, Some(Kdf::Bcrypt(16, 16)));
+            assert!(ssh_key.has_legacy_cipher());
+            assert!(!ssh_key.has_weak_kdf(16));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/ssh-ed25519-c-private-key-passphrase.pem") {
+        Leaf::SshKey(path, ssh_key) => {
+            assert_eq!(ssh_key.cipher, Some("aes256-ctr".into()));
+            assert_eq!(ssh_key.kdf, Some(Kdf::Bcrypt(16, 4)));
+            assert!(!ssh_key.has_legacy_cipher());
+            assert!(ssh_key.has_weak_kdf(16));
+            let output = format!("{}", Leaf::SshKey(path, ssh_key));
+            assert!(output.contains("encrypted with aes256-ctr"));
+            assert!(output.contains("bcrypt kdf, 4 rounds, 16 byte salt"));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/ssh-ed25519-a-private-key.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert_eq!(ssh_key.cipher, None);
+            assert_eq!(ssh_key.kdf, None);
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn openssh_passphrase_decryption() {
+    match decrypt(
+        "./files/ssh-ed25519-b-private-key-passphrase.pem",
+        "password",
+    ) {
+        Ok(Leaf::SshKey(_path, ssh_key)) => {
+            assert!(ssh_key.is_encrypted);
+            assert!(ssh_key.is_decrypted);
+            match ssh_key.algorithm {
+                Algorithm::Ed25519(ref point) => assert_eq!(point.len(), 32),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert!(ssh_key.errors.is_empty());
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    let ciphers = [
+        ("ssh-rsa-2048-c", "aes256-gcm@openssh.com"),
+        ("ssh-ecdsa-256-b", "chacha20-poly1305@openssh.com"),
+        ("ssh-ed25519-c", "aes256-ctr"),
+        ("ssh-ed25519-d", "aes128-cbc"),
+        ("ssh-ed25519-e", "3des-cbc"),
+    ];
+    for &(name, cipher) in ciphers.iter() {
+        let path = format!("./files/{}-private-key-passphrase.pem", name);
+        match (
+            decrypt(&path, "tealeaves"),
+            scan(&format!("./files/{}-public-key", name)),
+        ) {
+            (Ok(Leaf::SshKey(_, private_key)), Leaf::SshKey(_, public_key)) => {
+                assert_eq!(private_key.cipher, Some(cipher.into()));
+                assert!(private_key.is_decrypted, "{}", name);
+                assert!(private_key.is_pair(&public_key), "{}", name);
+                assert!(private_key.comment.is_some(), "{}", name);
+                assert!(private_key.errors.is_empty(), "{}", name);
+            }
+            _ => panic!("Expected SshKey for {}", name),
+        }
+    }
+}
+
+#[test]
+fn openssh_decrypted_rsa_shows_bits() {
+    match decrypt(
+        "./files/ssh-rsa-2048-c-private-key-passphrase.pem",
+        "tealeaves",
+    ) {
+        Ok(leaf) => {
+            let output = format!("{}", leaf);
+            assert!(output.contains("rsa, 2048 bits, encrypted, decrypted"));
+        }
+        Err(error) => panic!("{}", error),
+    }
+}
+
+#[test]
+fn openssh_wrong_passphrase() {
+    for name in &["ssh-rsa-2048-c", "ssh-ecdsa-256-b", "ssh-ed25519-d"] {
+        let path = format!("./files/{}-private-key-passphrase.pem", name);
+        let error = decrypt(&path, "wrong").err().unwrap();
+        assert!(error.contains("Incorrect passphrase"), "{}", error);
+    }
+}
+
+#[test]
+fn legacy_pem_dek_info() {
+    match scan("./files/ssh-dsa-1024-b-private-key-passphrase.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert_eq!(ssh_key.cipher, Some("AES-128-CBC".into()));
+            assert_eq!(
+                ssh_key.cipher_iv,
+                Some(vec![
+                    0x38, 0x63, 0x92, 0xD9, 0xF1, 0x29, 0xD1, 0x7D, 0x2E, 0x6E, 0x85, 0x70, 0x4A,
+                    0x9B, 0x7F, 0x14,
+                ])
+            );
+            assert_eq!(ssh_key.kdf, Some(Kdf::OpensslMd5));
+            // CBC is all legacy PEM encryption offers
+            assert!(!ssh_key.has_legacy_cipher());
+            assert!(!ssh_key.has_weak_kdf(1));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/ssh-ecdsa-256-a-private-key-passphrase.pem") {
+        Leaf::SshKey(path, ssh_key) => {
+            assert_eq!(ssh_key.cipher, Some("DES-EDE3-CBC".into()));
+            let output = format!("{}", Leaf::SshKey(path, ssh_key));
+            assert!(output.contains("private ssh key (ecdsa, encrypted)"));
+            assert!(output.contains("encrypted with DES-EDE3-CBC, IV AC6306C134BAD56D"));
+            assert!(output.contains(
+                "\n\t⚠️ legacy des cipher, re-encrypt with openssl pkcs8 -topk8 -v2 aes-256-cbc"
+            ));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn legacy_pem_md5_kdf() {
+    let mut count = 0;
+    for entry in fs::read_dir("./files").unwrap() {
+        let path = entry.unwrap().path();
+        if !path.is_file() {
+            continue;
+        }
+        let bytes = fs::read(&path).unwrap();
+        let header = b"Proc-Type: 4,ENCRYPTED";
+        if !bytes.windows(header.len()).any(|window| window == header) {
+            continue;
+        }
+        count += 1;
+        match scan(path.to_str().unwrap()) {
+            Leaf::SshKey(path, ssh_key) => {
+                assert!(!ssh_key.has_weak_kdf(1), "{}", path.display());
+                let output = format!("{}", Leaf::SshKey(path, ssh_key));
+                assert!(output.contains(
+                    "\n\t⚠️ single-pass md5 key derivation, convert with ssh-keygen -p -o \
+                     or openssl pkcs8 -topk8 -v2 aes-256-cbc"
+                ));
+                assert!(!output.contains("kdf rounds"));
+            }
+            _ => panic!("Expected SshKey for {}", path.display()),
+        }
+    }
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn legacy_pem_decryption() {
+    match decrypt(
+        "./files/tls-rsa-1024-passphrase-private-key.pem",
+        "password",
+    ) {
+        Ok(Leaf::SshKey(_path, ssh_key)) => {
+            assert!(ssh_key.is_decrypted);
+            match ssh_key.algorithm {
+                Algorithm::Rsa(ref modulus) => assert_eq!(modulus.len(), 256),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(
+                ssh_key.fingerprint_sha256(),
+                Some("SHA256:eantDKBtu0MZOpUUZA/z3S5lfP7pDKAEiRJPkUFlOYc".into())
+            );
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    let pairs = [
+        ("ssh-dsa-1024-b", "password"),
+        ("ssh-rsa-2048-b", "password"),
+        ("ssh-ecdsa-256-a", "tealeaves"),
+    ];
+    for &(name, passphrase) in pairs.iter() {
+        let path = format!("./files/{}-private-key-passphrase.pem", name);
+        match (
+            decrypt(&path, passphrase),
+            scan(&format!("./files/{}-public-key", name)),
+        ) {
+            (Ok(Leaf::SshKey(_, private_key)), Leaf::SshKey(_, public_key)) => {
+                assert!(private_key.is_decrypted, "{}", name);
+                assert!(private_key.is_pair(&public_key), "{}", name);
+            }
+            _ => panic!("Expected SshKey for {}", name),
+        }
+    }
+    for name in &[
+        "tls-rsa-1024-passphrase-private-key.pem",
+        "ssh-ecdsa-256-a-private-key-passphrase.pem",
+    ] {
+        let error = decrypt(&format!("./files/{}", name), "wrong")
+            .err()
+            .unwrap();
+        assert_eq!(error, "Incorrect passphrase");
+    }
+}
+
+#[test]
+fn pkcs8_private_keys_pair() {
+    for name in &[
+        "pkcs8-rsa-2048",
+        "pkcs8-dsa-1024",
+        "pkcs8-ecdsa-384",
+        "pkcs8-ed25519",
+    ] {
+        let private_path = format!("./files/{}-private-key.pem", name);
+        let public_path = format!("./files/{}-public-key", name);
+        match (scan(&private_path), scan(&public_path)) {
+            (Leaf::SshKey(_, private_key), Leaf::SshKey(_, public_key)) => {
+                assert!(!private_key.is_encrypted, "{}", name);
+                assert!(private_key.is_pair(&public_key), "{}", name);
+                assert_eq!(
+                    private_key.fingerprint_sha256(),
+                    public_key.fingerprint_sha256()
+                );
+            }
+            _ => panic!("Expected SshKey for {}", name),
+        }
+    }
+    match scan("./files/pkcs8-x25519-private-key.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert_eq!(ssh_key.algorithm, Algorithm::X25519);
+            assert_eq!(ssh_key.fingerprint_sha256(), None);
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn pkcs8_trailing_data_gets_error() {
+    let path = "./files/pkcs8-rsa-2048-private-key-trailing-data.pem";
+    let error = tealeaves::scan(&rsfs::disk::FS, &path).err().unwrap();
+    assert_eq!(error, "Trailing data after the RSA private key");
+}
+
+#[test]
+fn pkcs8_encryption_details() {
+    match scan("./files/pkcs8-rsa-2048-private-key-passphrase.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert!(ssh_key.is_encrypted);
+            assert_eq!(ssh_key.algorithm, Algorithm::Unknown);
+            assert_eq!(ssh_key.cipher, Some("aes-256-cbc".into()));
+            assert_eq!(
+                ssh_key.kdf,
+                Some(Kdf::Pbkdf2("hmacWithSHA256".into(), 100_000, 16))
+            );
+            assert!(!ssh_key.has_legacy_cipher());
+            assert!(!ssh_key.has_weak_pbe());
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/pkcs8-ecdsa-384-private-key-passphrase.pem") {
+        Leaf::SshKey(path, ssh_key) => {
+            assert_eq!(ssh_key.cipher, Some("des-ede3-cbc".into()));
+            assert_eq!(
+                ssh_key.kdf,
+                Some(Kdf::Pbkdf2("hmacWithSHA512".into(), 1000, 16))
+            );
+            assert!(ssh_key.has_weak_pbe());
+            assert!(ssh_key.has_legacy_cipher());
+            let output = format!("{}", Leaf::SshKey(path, ssh_key));
+            assert!(output.contains("fewer than 10000 pbkdf2 iterations"));
+            assert!(!output.contains("ssh-keygen -p"));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/pkcs8-dsa-1024-private-key-pbes1.pem") {
+        Leaf::SshKey(path, ssh_key) => {
+            assert_eq!(
+                ssh_key.kdf,
+                Some(Kdf::Pbes1("pbeWithSHAAnd3-KeyTripleDES-CBC".into()))
+            );
+            let output = format!("{}", Leaf::SshKey(path, ssh_key));
+            assert!(output.contains("legacy pbes1 encryption"));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/pkcs8-x25519-private-key-scrypt.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert_eq!(ssh_key.kdf, Some(Kdf::Scrypt(16_384, 8, 1)));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn pkcs8_decryption() {
+    for name in &["pkcs8-rsa-2048", "pkcs8-ecdsa-384", "pkcs8-ed25519"] {
+        let private_path = format!("./files/{}-private-key-passphrase.pem", name);
+        let public_path = format!("./files/{}-public-key", name);
+        match (decrypt(&private_path, "tealeaves"), scan(&public_path)) {
+            (Ok(Leaf::SshKey(_, private_key)), Leaf::SshKey(_, public_key)) => {
+                assert!(private_key.is_decrypted, "{}", name);
+                assert!(private_key.is_pair(&public_key), "{}", name);
+            }
+            _ => panic!("Expected SshKey for {}", name),
+        }
+        let error = decrypt(&private_path, "wrong").err().unwrap();
+        assert_eq!(error, "Incorrect passphrase");
+    }
+    assert!(decrypt("./files/pkcs8-dsa-1024-private-key-pbes1.pem", "tealeaves").is_err());
+}
+
+#[test]
+fn putty_ppk_details() {
+    match scan("./files/putty-ed25519-v3-private-key.ppk") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert!(!ssh_key.is_public);
+            assert!(!ssh_key.is_encrypted);
+            assert_eq!(ssh_key.comment, Some("unit test comment".into()));
+            assert_eq!(ssh_key.kdf, None);
+            assert_eq!(
+                format!("{}", ssh_key),
+                "private key in PuTTY PPK v3 format (ed25519, not encrypted)"
+            );
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/putty-ed25519-v3-private-key-passphrase.ppk") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert!(ssh_key.is_encrypted);
+            assert!(!ssh_key.is_decrypted);
+            assert_eq!(ssh_key.cipher, Some("aes256-cbc".into()));
+            assert_eq!(
+                ssh_key.kdf,
+                Some(Kdf::Argon2("Argon2id".into(), 8192, 13, 1))
+            );
+            assert!(!ssh_key.has_legacy_cipher());
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/putty-rsa-2048-v2-private-key-passphrase.ppk") {
+        Leaf::SshKey(path, ssh_key) => {
+            assert_eq!(ssh_key.comment, Some("rsa-key-20181020".into()));
+            assert_eq!(ssh_key.kdf, Some(Kdf::PuttySha1));
+            let details = format!("{}", Leaf::SshKey(path, ssh_key));
+            assert!(details.contains("private key in PuTTY PPK v2 format (rsa, encrypted)"));
+            assert!(details.contains("re-save as PPK v3 in puttygen"));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn putty_ppk_pairs() {
+    for &(ppk, public) in &[
+        ("putty-ed25519-v3-private-key", "ssh-ed25519-a-public-key"),
+        (
+            "putty-ed25519-v3-private-key-passphrase",
+            "ssh-ed25519-a-public-key",
+        ),
+        ("putty-rsa-2048-v2-private-key", "ssh-rsa-2048-a-public-key"),
+        (
+            "putty-rsa-2048-v2-private-key-passphrase",
+            "ssh-rsa-2048-a-public-key",
+        ),
+    ] {
+        let ppk_path = format!("./files/{}.ppk", ppk);
+        let public_path = format!("./files/{}", public);
+        match (scan(&ppk_path), scan(&public_path)) {
+            (Leaf::SshKey(_, private_key), Leaf::SshKey(_, public_key)) => {
+                assert!(private_key.is_pair(&public_key), "{}", ppk);
+            }
+            _ => panic!("Expected SshKey for {}", ppk),
+        }
+    }
+}
+
+#[test]
+fn putty_ppk_decryption() {
+    for path in &[
+        "./files/putty-ed25519-v3-private-key-passphrase.ppk",
+        "./files/putty-rsa-2048-v2-private-key-passphrase.ppk",
+    ] {
+        match decrypt(path, "tealeaves") {
+            Ok(Leaf::SshKey(_path, ssh_key)) => {
+                assert!(ssh_key.is_encrypted);
+                assert!(ssh_key.is_decrypted, "{}", path);
+            }
+            _ => panic!("Expected SshKey for {}", path),
+        }
+        match decrypt(path, "wrong") {
+            Ok(Leaf::Error(_path, message)) => assert_eq!(message, "Incorrect passphrase"),
+            _ => panic!("Expected an error for {}", path),
+        }
+    }
+}
+
+#[test]
+fn putty_ppk_corrupt_mac() {
+    let bytes = std::fs::read("./files/putty-rsa-2048-v2-private-key.ppk").unwrap();
+    let text = String::from_utf8(bytes).unwrap();
+    let tampered = text.replace("Comment: rsa-key-20181020", "Comment: rsa-key-20181021");
+    assert_eq!(
+        tealeaves::ppk::parse(tampered.as_bytes(), None).err(),
+        Some("Private-MAC mismatch, the key file is corrupt".into())
+    );
+}
+
+#[test]
+fn putty_ppk_oversized_argon2() {
+    let bytes = std::fs::read("./files/putty-ed25519-v3-private-key-passphrase.ppk").unwrap();
+    let text = String::from_utf8(bytes).unwrap();
+    for &(from, to, error) in &[
+        (
+            "Argon2-Memory: 8192",
+            "Argon2-Memory: 4294967295",
+            "Argon2 parameters too large: 4294967295 KiB, 13 passes, parallelism 1",
+        ),
+        (
+            "Argon2-Passes: 13",
+            "Argon2-Passes: 100000",
+            "Argon2 parameters too large: 8192 KiB, 100000 passes, parallelism 1",
+        ),
+        (
+            "Argon2-Parallelism: 1",
+            "Argon2-Parallelism: 255",
+            "Argon2 parameters too large: 8192 KiB, 13 passes, parallelism 255",
+        ),
+    ] {
+        let tampered = text.replace(from, to);
+        assert_eq!(
+            tealeaves::ppk::parse(tampered.as_bytes(), Some(b"tealeaves")).err(),
+            Some(error.into())
+        );
+    }
+}
+
+#[test]
+fn sshcom_private_keys() {
+    for &(private, public) in &[
+        ("sshcom-rsa-2048-private-key", "ssh-rsa-2048-a-public-key"),
+        ("sshcom-dsa-1024-private-key", "ssh-dsa-1024-a-public-key"),
+    ] {
+        match (
+            scan(&format!("./files/{}", private)),
+            scan(&format!("./files/{}", public)),
+        ) {
+            (Leaf::SshKey(_, private_key), Leaf::SshKey(_, public_key)) => {
+                assert!(!private_key.is_public);
+                assert!(!private_key.is_encrypted);
+                assert_eq!(private_key.legacy_format, Some("ssh.com".into()));
+                assert!(private_key.is_pair(&public_key), "{}", private);
+                assert_eq!(
+                    private_key.fingerprint_sha256(),
+                    public_key.fingerprint_sha256()
+                );
+            }
+            _ => panic!("Expected SshKey for {}", private),
+        }
+    }
+    match scan("./files/sshcom-rsa-2048-private-key-passphrase") {
+        leaf @ Leaf::SshKey(..) => {
+            assert!(format!("{}", leaf).contains("🔥 legacy ssh.com key format"));
+            assert!(format!("{}", leaf).contains("⚠️ unsalted md5 key derivation"));
+            if let Leaf::SshKey(_path, ssh_key) = leaf {
+                assert!(ssh_key.is_encrypted);
+                assert_eq!(ssh_key.algorithm, Algorithm::Rsa(vec![]));
+                assert_eq!(ssh_key.cipher, Some("3des-cbc".into()));
+                assert_eq!(ssh_key.kdf, Some(Kdf::SshcomMd5));
+                assert_eq!(
+                    ssh_key.comment,
+                    Some("[2048-bit rsa, tealeaves@example.com]".into())
+                );
+                assert!(!ssh_key.has_legacy_cipher());
+            }
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
@@ -127,3 +127,308 @@ fn ecdsa_521_public() {
         _ => panic!("Expected SshKey"),
     }
 }
+
+#[test]
+fn ed25519_sk_public() {
+    match scan("./files/ssh-ed25519-sk-a-public-key") {
+        Leaf::SshKey(_path, ssh_key) => {
+            match ssh_key.algorithm {
+                Algorithm::SkEd25519(ref point, ref security_key) => {
+                    assert_eq!(point.len(), 32);
+                    assert_eq!(security_key.application, "ssh:");
+                    assert_eq!(security_key.flags, None);
+                }
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(ssh_key.is_public, true);
+            assert_eq!(ssh_key.comment, Some("unit test comment".into()));
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn ecdsa_sk_public() {
+    match scan("./files/ssh-ecdsa-sk-256-a-public-key") {
+        Leaf::SshKey(_path, ssh_key) => {
+            match ssh_key.algorithm {
+                Algorithm::SkEcdsa(ref curve, ref point, ref security_key) => {
+                    assert_eq!(curve, "nistp256");
+                    assert_eq!(point.len(), 65);
+                    assert_eq!(security_key.application, "ssh:");
+                }
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(ssh_key.is_public, true);
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn fingerprints_match_ssh_keygen() {
+    let fixtures = [
+        (
+            "./files/ssh-dsa-1024-a-public-key",
+            "SHA256:bH3vQYE2ewtSf1vjxb0t4sc5ER9cv8toO87u6KOJWb4",
+            "MD5:16:a0:09:90:3f:81:60:43:4d:42:a6:11:6c:20:da:05",
+        ),
+        (
+            "./files/ssh-dsa-1024-d-public",
+            "SHA256:FNIYXCMEDleuAVEeFvh7JTE2QvEgdMbWiuS1Fw4nPbg",
+            "MD5:45:79:66:ff:84:b6:1c:3b:ac:4b:9b:72:28:76:ea:03",
+        ),
+        (
+            "./files/ssh-ecdsa-256-a-public-key",
+            "SHA256:aztC83ECiE/mEH73NDZlXwzeiow/OCSbyKv8LHS6TyM",
+            "MD5:1e:68:a2:78:9c:88:e3:67:88:2f:ee:c3:fc:8d:20:7e",
+        ),
+        (
+            "./files/ssh-ecdsa-384-a-public-key",
+            "SHA256:keo+0O5I1kWvFfiB/ZtALeNohuuDL/TRbRKHZM+blls",
+            "MD5:97:2e:9d:2d:ef:d1:ff:71:21:10:1a:89:3a:d8:dd:fe",
+        ),
+        (
+            "./files/ssh-ecdsa-521-a-public-key",
+            "SHA256:wxKqHU2+QkhrucBovm3NIL2JRzx0L7wPVgPSoT4AODg",
+            "MD5:67:ac:68:8e:42:01:d2:13:c7:72:e9:6c:83:f3:10:4c",
+        ),
+        (
+            "./files/ssh-ecdsa-sk-256-a-public-key",
+            "SHA256:UdA4JAoFDFVF54NNvPioUU3o6MAgdTRne/STnfDbJ9k",
+            "MD5:7b:4e:d4:3a:8f:df:ec:54:32:17:da:4a:08:32:50:eb",
+        ),
+        (
+            "./files/ssh-ed25519-a-public-key",
+            "SHA256:VK3YHtc+HoYqK+e3/UBrrLV7//T6zpd5bza7ITP/kEw",
+            "MD5:c1:65:b1:9b:f6:94:10:78:76:8d:dc:2d:cb:c7:a0:eb",
+        ),
+        (
+            "./files/ssh-ed25519-sk-a-public-key",
+            "SHA256:EDgbijjQ+E3Wf/F8OgpINtab5ITFHDGdAhMvyuNUvqU",
+            "MD5:37:70:d1:f5:c9:73:06:24:8b:12:99:df:dc:3f:d0:35",
+        ),
+        (
+            "./files/ssh-rsa-1024-a-public-key",
+            "SHA256:+1KRzoZVAaxtRinJwaD02LNXNaw9zp/dgcJzpxmNFn8",
+            "MD5:ff:dd:06:c4:0e:a3:5a:d9:5b:cb:f1:dd:45:85:df:06",
+        ),
+        (
+            "./files/ssh-rsa-4096-a-public-key",
+            "SHA256:NMGYc2WXxvOp6OiWTytd52n5/jCsRgKbzZr6EcecagY",
+            "MD5:75:ef:f3:2c:8b:1d:71:7c:97:23:26:4f:87:5b:45:71",
+        ),
+    ];
+    for &(path, sha256, md5) in &fixtures {
+        match scan(path) {
+            Leaf::SshKey(_path, ssh_key) => {
+                assert_eq!(ssh_key.fingerprint_sha256(), Some(sha256.into()));
+                assert_eq!(ssh_key.fingerprint_md5(), Some(md5.into()));
+            }
+            _ => panic!("Expected SshKey"),
+        }
+    }
+}
+
+#[test]
+fn randomart_matches_ssh_keygen() {
+    match scan("./files/ssh-ed25519-a-public-key") {
+        Leaf::SshKey(_path, ssh_key) => assert_eq!(
+            ssh_key.randomart(),
+            Some(
+                "+--[ED25519 256]--+
+|          ..     |
+|         .  .    |
+|        .o . .   |
+|       .. + . .  |
+|        S. o.oE  |
+|          .o.+=. |
+|           .**+++|
+|      . o o= oBBB|
+|       +o+o.++o@^|
++----[SHA256]-----+"
+                    .into()
+            )
+        ),
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/ssh-rsa-4096-a-public-key") {
+        Leaf::SshKey(_path, ssh_key) => assert_eq!(
+            ssh_key.randomart(),
+            Some(
+                "+---[RSA 4096]----+
+|       +..o...   |
+|      + oo .=    |
+|       oo  . o . |
+|       ..+ .  o  |
+|      E S*=. .   |
+|       .o+= +..  |
+|        =B.+ o+o |
+|       oBoo...=o |
+|      .=ooo... o=|
++----[SHA256]-----+"
+                    .into()
+            )
+        ),
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/ssh-ed25519-sk-a-public-key") {
+        Leaf::SshKey(_path, ssh_key) => assert_eq!(
+            ssh_key.randomart(),
+            Some(
+                "+[ED25519-SK 256]-+
+| o   o==++ .     |
+|o . * .+=.+      |
+|oo = ++=.. +     |
+|+ o.o+*o= . o .  |
+| .  =+.*S+   o   |
+|   o ..+=   o    |
+|    . E  . . .   |
+|          .      |
+|                 |
++----[SHA256]-----+"
+                    .into()
+            )
+        ),
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn pem_public_keys() {
+    for &(name, format) in &[
+        ("tls-rsa-2048-public-key", "PEM PUBLIC KEY"),
+        ("tls-rsa-2048-rsa-public-key", "PEM RSA PUBLIC KEY"),
+    ] {
+        match scan(&format!("./files/{}.pem", name)) {
+            Leaf::SshKey(_path, ssh_key) => {
+                match ssh_key.algorithm {
+                    Algorithm::Rsa(ref modulus) => assert_eq!(modulus.len() * 8, 2048),
+                    _ => panic!("algorithm not detected correctly"),
+                };
+                assert_eq!(ssh_key.is_public, true);
+                assert_eq!(ssh_key.comment, None);
+                assert_eq!(ssh_key.format, Some(format.into()));
+                assert_eq!(
+                    format!("{}", ssh_key),
+                    format!("public key in {} format (rsa, 2048 bits)", format)
+                );
+            }
+            _ => panic!("Expected SshKey for {}", name),
+        }
+    }
+    match scan("./files/pkcs8-dsa-1024-public-key.pem") {
+        Leaf::SshKey(_path, ssh_key) => match ssh_key.algorithm {
+            Algorithm::Dsa(p) => assert_eq!(p.len() * 8, 1024),
+            _ => panic!("algorithm not detected correctly"),
+        },
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/pkcs8-ecdsa-384-public-key.pem") {
+        Leaf::SshKey(_path, ssh_key) => match ssh_key.algorithm {
+            Algorithm::Ecdsa(curve, point) => {
+                assert_eq!(curve, "nistp384");
+                assert_eq!(point.len(), 97);
+            }
+            _ => panic!("algorithm not detected correctly"),
+        },
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/pkcs8-ed25519-public-key.pem") {
+        Leaf::SshKey(_path, ssh_key) => match ssh_key.algorithm {
+            Algorithm::Ed25519(point) => assert_eq!(point.len(), 32),
+            _ => panic!("algorithm not detected correctly"),
+        },
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/pkcs8-x25519-public-key.pem") {
+        Leaf::SshKey(_path, ssh_key) => {
+            assert_eq!(ssh_key.algorithm, Algorithm::X25519);
+            assert_eq!(ssh_key.is_public, true);
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn pem_public_keys_pair() {
+    for &(public_name, private_name) in &[
+        ("tls-rsa-2048-public-key", "tls-rsa-2048-private-key"),
+        ("tls-rsa-2048-rsa-public-key", "tls-rsa-2048-private-key"),
+        ("pkcs8-dsa-1024-public-key", "pkcs8-dsa-1024-private-key"),
+        ("pkcs8-ecdsa-384-public-key", "pkcs8-ecdsa-384-private-key"),
+        ("pkcs8-ed25519-public-key", "pkcs8-ed25519-private-key"),
+    ] {
+        let public_path = format!("./files/{}.pem", public_name);
+        let private_path = format!("./files/{}.pem", private_name);
+        match (scan(&public_path), scan(&private_path)) {
+            (Leaf::SshKey(_, public_key), Leaf::SshKey(_, private_key)) => {
+                assert!(private_key.is_pair(&public_key), "{}", public_name);
+                assert_eq!(
+                    public_key.fingerprint_sha256(),
+                    private_key.fingerprint_sha256()
+                );
+            }
+            _ => panic!("Expected SshKey for {}", public_name),
+        }
+    }
+}
+
+#[test]
+fn rfc4716_public_keys() {
+    match scan("./files/ssh2-ed25519-a-public-key") {
+        Leaf::SshKey(_path, ssh_key) => {
+            match ssh_key.algorithm {
+                Algorithm::Ed25519(ref point) => assert_eq!(point.len(), 32),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(ssh_key.is_public, true);
+            assert_eq!(
+                ssh_key.comment,
+                Some("256-bit ED25519, converted by root@vm from OpenSSH".into())
+            );
+            assert_eq!(
+                format!("{}", ssh_key),
+                "public key in RFC 4716 format (ed25519)"
+            );
+        }
+        _ => panic!("Expected SshKey"),
+    }
+    match scan("./files/ssh2-rsa-2048-a-public-key") {
+        Leaf::SshKey(_path, ssh_key) => {
+            match ssh_key.algorithm {
+                Algorithm::Rsa(ref modulus) => assert_eq!(modulus.len() * 8, 2048),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(
+                ssh_key.comment,
+                Some(
+                    "2048-bit RSA, converted from OpenSSH for the tealeaves unit tests, \
+                     with a folded header line"
+                        .into()
+                )
+            );
+        }
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn rfc4716_public_keys_pair() {
+    for name in &["ed25519-a", "rsa-2048-a"] {
+        let rfc4716_path = format!("./files/ssh2-{}-public-key", name);
+        let openssh_path = format!("./files/ssh-{}-public-key", name);
+        let private_path = format!("./files/ssh-{}-private-key.pem", name);
+        match (
+            scan(&rfc4716_path),
+            scan(&openssh_path),
+            scan(&private_path),
+        ) {
+            (Leaf::SshKey(_, rfc4716), Leaf::SshKey(_, openssh), Leaf::SshKey(_, private_key)) => {
+                assert_eq!(rfc4716.public_blob, openssh.public_blob);
+                assert!(private_key.is_pair(&rfc4716), "{}", name);
+            }
+            _ => panic!("Expected SshKey for {}", name),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+extern crate rsfs;
+extern crate tealeaves;
+use tealeaves::ssh_certificate::CertificateType;
+use tealeaves::ssh_key::Algorithm;
+use tealeaves::Leaf;
+
+fn scan(path: &str) -> Leaf {
+    tealeaves::scan(&rsfs::disk::FS, &path).unwrap()
+}
+
+#[test]
+fn ed25519_user_certificate() {
+    match scan("./files/ssh-ed25519-a-cert") {
+        Leaf::SshCertificate(_path, cert) => {
+            assert_eq!(cert.key_type, "ssh-ed25519-cert-v01@openssh.com");
+            assert_eq!(cert.nonce.len(), 32);
+            match cert.key.algorithm {
+                Algorithm::Ed25519(ref point) => assert_eq!(point.len(), 32),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(
+                cert.key.fingerprint_sha256(),
+                Some("SHA256:VK3YHtc+HoYqK+e3/UBrrLV7//T6zpd5bza7ITP/kEw".into())
+            );
+            assert_eq!(cert.key.comment, Some("unit test comment".into()));
+            assert_eq!(cert.serial, 42);
+            assert_eq!(cert.cert_type, CertificateType::User);
+            assert_eq!(cert.key_id, "tealeaves-unit-test");
+            assert_eq!(cert.principals, vec!["alice", "deploy"]);
+            assert_eq!(cert.valid_after, 1_514_764_800);
+            assert_eq!(cert.valid_before, 2_145_916_800);
+            assert_eq!(
+                cert.critical_options,
+                vec![("force-command".into(), "/usr/bin/true".into())]
+            );
+            assert_eq!(cert.extensions.len(), 5);
+            assert_eq!(cert.extensions[3], ("permit-pty".into(), "".into()));
+            assert_eq!(
+                cert.signature_key.fingerprint_sha256(),
+                Some("SHA256:4wltAvH9u4xnY+h7Mk7r/0TVo5Wqa4OIDO20w+JF854".into())
+            );
+            assert_eq!(cert.signature_algorithm, "ssh-ed25519");
+            assert_eq!(cert.signature.len(), 64);
+            assert!(!cert.is_sha1_signed());
+            assert!(!cert.is_not_yet_valid());
+        }
+        _ => panic!("Expected SshCertificate"),
+    }
+}
+
+#[test]
+fn rsa_expired_host_certificate() {
+    match scan("./files/ssh-rsa-2048-a-cert") {
+        Leaf::SshCertificate(path, cert) => {
+            assert_eq!(cert.cert_type, CertificateType::Host);
+            match cert.key.algorithm {
+                Algorithm::Rsa(ref modulus) => assert_eq!(modulus.len() * 8, 2048),
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert!(cert.principals.is_empty());
+            assert!(cert.critical_options.is_empty());
+            assert!(cert.extensions.is_empty());
+            assert_eq!(cert.signature_algorithm, "ssh-rsa");
+            assert!(cert.is_sha1_signed());
+            assert!(cert.is_expired());
+            let output = format!("{}", Leaf::SshCertificate(path, cert));
+            assert!(output.contains("🚨 expired"));
+            assert!(output.contains("⚠️ no principals, valid for any host"));
+            assert!(output.contains("🚨 CA signature uses ssh-rsa (SHA-1)"));
+        }
+        _ => panic!("Expected SshCertificate"),
+    }
+}
+
+#[test]
+fn ecdsa_future_certificate() {
+    match scan("./files/ssh-ecdsa-256-a-cert") {
+        Leaf::SshCertificate(path, cert) => {
+            match cert.key.algorithm {
+                Algorithm::Ecdsa(ref curve, ref point) => {
+                    assert_eq!(curve, "nistp256");
+                    assert_eq!(point.len(), 65);
+                }
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert!(cert.is_not_yet_valid());
+            assert!(!cert.is_expired());
+            let output = format!("{}", Leaf::SshCertificate(path, cert));
+            assert!(output.contains("⚠️ not yet valid"));
+        }
+        _ => panic!("Expected SshCertificate"),
+    }
+}
+
+#[test]
+fn ed25519_sk_certificate() {
+    match scan("./files/ssh-ed25519-sk-a-cert") {
+        Leaf::SshCertificate(_path, cert) => {
+            assert_eq!(cert.key_type, "sk-ssh-ed25519-cert-v01@openssh.com");
+            match cert.key.algorithm {
+                Algorithm::SkEd25519(ref point, ref security_key) => {
+                    assert_eq!(point.len(), 32);
+                    assert_eq!(security_key.application, "ssh:");
+                }
+                _ => panic!("algorithm not detected correctly"),
+            };
+            assert_eq!(
+                cert.key.fingerprint_sha256(),
+                Some("SHA256:EDgbijjQ+E3Wf/F8OgpINtab5ITFHDGdAhMvyuNUvqU".into())
+            );
+            assert_eq!(cert.principals, vec!["carol"]);
+            assert!(cert.certifies(&ssh_key("./files/ssh-ed25519-sk-a-private-key.pem")));
+            assert!(cert.is_signed_by(&ssh_key("./files/ssh-ed25519-ca-public-key")));
+        }
+        _ => panic!("Expected SshCertificate"),
+    }
+}
+
+fn ssh_key(path: &str) -> tealeaves::ssh_key::SshKey {
+    match scan(path) {
+        Leaf::SshKey(_path, ssh_key) => ssh_key,
+        _ => panic!("Expected SshKey"),
+    }
+}
+
+#[test]
+fn certificate_pairs_with_private_and_ca_keys() {
+    match scan("./files/ssh-rsa-2048-a-cert") {
+        Leaf::SshCertificate(_path, cert) => {
+            assert!(cert.certifies(&ssh_key("./files/ssh-rsa-2048-a-private-key.pem")));
+            assert!(!cert.certifies(&ssh_key("./files/ssh-rsa-4096-a-private-key.pem")));
+            assert!(cert.is_signed_by(&ssh_key("./files/ssh-rsa-4096-a-private-key.pem")));
+            assert!(cert.is_signed_by(&ssh_key("./files/ssh-rsa-4096-a-public-key")));
+            assert!(!cert.is_signed_by(&ssh_key("./files/ssh-ed25519-ca-public-key")));
+        }
+        _ => panic!("Expected SshCertificate"),
+    }
+    match scan("./files/ssh-ed25519-a-cert") {
+        Leaf::SshCertificate(_path, cert) => {
+            assert!(cert.certifies(&ssh_key("./files/ssh-ed25519-a-private-key.pem")));
+            assert!(cert.is_signed_by(&ssh_key("./files/ssh-ed25519-ca-public-key")));
+            assert!(cert.is_signed_by(&ssh_key("./files/ssh-ed25519-ca-private-key.pem")));
+        }
+        _ => panic!("Expected SshCertificate"),
+    }
+}
@@ -0,0 +1,330 @@
+extern crate rsfs;
+extern crate tealeaves;
+mod common;
+use common::{copy_fixture, write};
+use rsfs::mem::unix::FS;
+use rsfs::GenFS;
+use std::path::PathBuf;
+use tealeaves::leaf::Leaf;
+use tealeaves::ssh_config::{self, Local, Severity};
+
+fn memfs() -> FS {
+    let fs = FS::new();
+    fs.create_dir_all("/home/a/.ssh/config.d").unwrap();
+    fs.create_dir_all("/etc/ssh").unwrap();
+    copy_fixture(
+        &fs,
+        "./files/ssh-ed25519-a-private-key.pem",
+        "/home/a/.ssh/id_ed25519",
+    );
+    copy_fixture(
+        &fs,
+        "./files/ssh-rsa-2048-a-private-key.pem",
+        "/home/a/.ssh/work_rsa",
+    );
+    copy_fixture(
+        &fs,
+        "./files/ssh-ecdsa-256-a-private-key.pem",
+        "/home/a/.ssh/spare",
+    );
+    copy_fixture(
+        &fs,
+        "./files/ssh-rsa-4096-a-private-key.pem",
+        "/home/a/.ssh/github.com_key",
+    );
+    write(
+        &fs,
+        "/home/a/.ssh/config",
+        b"# Work hosts
+Include config.d/*.conf
+
+Host github
+    HostName github.com
+    User git
+    IdentityFile ~/.ssh/%h_key
+    IdentityFile ~/.ssh/id_ed25519
+
+Match host old-* user a
+    IdentityFile %d/.ssh/%u_%n
+
+Host legacy
+    Include legacy.conf
+",
+    );
+    write(
+        &fs,
+        "/home/a/.ssh/config.d/work.conf",
+        b"Host work bastion
+    IdentityFile ~/.ssh/work_rsa
+    CertificateFile ~/.ssh/work_rsa-cert.pub
+",
+    );
+    // Relative to ~/.ssh, and only for Host legacy
+    write(
+        &fs,
+        "/home/a/.ssh/legacy.conf",
+        b"IdentityFile \"~/.ssh/legacy key\"
+",
+    );
+    write(
+        &fs,
+        "/etc/ssh/ssh_config",
+        b"Host *
+    IdentityFile ~/.ssh/id_ed25519
+    SendEnv LANG LC_*
+",
+    );
+    fs
+}
+
+fn local() -> Local {
+    Local {
+        home: PathBuf::from("/home/a"),
+        user: "a".into(),
+    }
+}
+
+fn config_paths() -> Vec<PathBuf> {
+    vec![
+        PathBuf::from("/home/a/.ssh/config"),
+        PathBuf::from("/etc/ssh/ssh_config"),
+    ]
+}
+
+#[test]
+fn ssh_config_directives() {
+    let fs = memfs();
+    let config = ssh_config::read(&fs, &config_paths(), &local());
+    assert_eq!(config.errors, vec![]);
+    let files: Vec<(String, usize, &str)> = config
+        .directives
+        .iter()
+        .map(|directive| {
+            (
+                directive.file.display().to_string(),
+                directive.line,
+                directive.keyword.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        files[0],
+        ("/home/a/.ssh/config.d/work.conf".into(), 2, "identityfile")
+    );
+    assert_eq!(
+        files[7],
+        ("/home/a/.ssh/legacy.conf".into(), 1, "identityfile")
+    );
+    assert_eq!(files[9], ("/etc/ssh/ssh_config".into(), 3, "sendenv"));
+    assert_eq!(config.directives[9].arguments, vec!["LANG", "LC_*"]);
+    assert_eq!(
+        config.host_aliases(),
+        vec!["work", "bastion", "github", "legacy"]
+    );
+}
+
+#[test]
+fn ssh_config_identity_files() {
+    let fs = memfs();
+    let local = local();
+    let config = ssh_config::read(&fs, &config_paths(), &local);
+    let identity_files = |alias: &str| -> Vec<PathBuf> {
+        config
+            .identity_files(alias, &local)
+            .into_iter()
+            .map(|reference| reference.path)
+            .collect()
+    };
+    assert_eq!(
+        identity_files("github"),
+        vec![
+            PathBuf::from("/home/a/.ssh/github.com_key"),
+            PathBuf::from("/home/a/.ssh/id_ed25519"),
+            PathBuf::from("/home/a/.ssh/id_ed25519"),
+        ]
+    );
+    assert_eq!(
+        identity_files("old-db"),
+        vec![
+            PathBuf::from("/home/a/.ssh/a_old-db"),
+            PathBuf::from("/home/a/.ssh/id_ed25519"),
+        ]
+    );
+    assert_eq!(
+        identity_files("legacy")[0],
+        PathBuf::from("/home/a/.ssh/legacy key")
+    );
+    // The legacy Include only applies inside its Host block
+    assert_eq!(
+        identity_files("work"),
+        vec![
+            PathBuf::from("/home/a/.ssh/work_rsa"),
+            PathBuf::from("/home/a/.ssh/id_ed25519"),
+        ]
+    );
+}
+
+#[test]
+fn ssh_config_match_user_is_the_remote_user() {
+    let fs = FS::new();
+    write(
+        &fs,
+        "/home/a/.ssh/config",
+        b"Host deploy-*
+    User deploy
+
+Match user deploy
+    IdentityFile ~/.ssh/deploy_key
+
+Match user a
+    IdentityFile ~/.ssh/remote_a_key
+
+Match localuser a
+    IdentityFile ~/.ssh/id_ed25519
+",
+    );
+    let local = local();
+    let config = ssh_config::read(&fs, &[PathBuf::from("/home/a/.ssh/config")], &local);
+    let identity_files = |alias: &str| -> Vec<PathBuf> {
+        config
+            .identity_files(alias, &local)
+            .into_iter()
+            .map(|reference| reference.path)
+            .collect()
+    };
+    // deploy-web connects as deploy, though the local user is a
+    assert_eq!(
+        identity_files("deploy-web"),
+        vec![
+            PathBuf::from("/home/a/.ssh/deploy_key"),
+            PathBuf::from("/home/a/.ssh/id_ed25519"),
+        ]
+    );
+    assert_eq!(
+        identity_files("web"),
+        vec![
+            PathBuf::from("/home/a/.ssh/remote_a_key"),
+            PathBuf::from("/home/a/.ssh/id_ed25519"),
+        ]
+    );
+}
+
+#[test]
+fn ssh_config_key_usage() {
+    let fs = memfs();
+    let local = local();
+    let config = ssh_config::read(&fs, &config_paths(), &local);
+    copy_fixture(
+        &fs,
+        "./files/tls-rsa-2048-private-key.pem",
+        "/etc/ssh/tls-key.pem",
+    );
+    let leaves: Vec<Leaf> = [
+        "/home/a/.ssh/id_ed25519",
+        "/home/a/.ssh/work_rsa",
+        "/home/a/.ssh/spare",
+        "/home/a/.ssh/github.com_key",
+        "/etc/ssh/tls-key.pem",
+    ]
+    .iter()
+    .map(|path| tealeaves::scan(&fs, path).unwrap())
+    .collect();
+    let usage = ssh_config::key_usage(&fs, &config, &local, &leaves);
+    let missing: Vec<(usize, &str, String)> = usage
+        .missing
+        .iter()
+        .map(|reference| {
+            (
+                reference.line,
+                reference.keyword.as_str(),
+                reference.path.display().to_string(),
+            )
+        })
+        .collect();
+    // %n and %u only expand for aliases their Match block applies to,
+    // and no Host line names an old-* alias
+    assert_eq!(
+        missing,
+        vec![
+            (
+                3,
+                "CertificateFile",
+                "/home/a/.ssh/work_rsa-cert.pub".into()
+            ),
+            (1, "IdentityFile", "/home/a/.ssh/legacy key".into()),
+        ]
+    );
+    // Keys outside ~/.ssh are never reported as unused
+    assert_eq!(usage.unused, vec![PathBuf::from("/home/a/.ssh/spare")]);
+    assert_eq!(
+        usage.first_keys,
+        vec![
+            ("work".into(), Some(PathBuf::from("/home/a/.ssh/work_rsa"))),
+            (
+                "bastion".into(),
+                Some(PathBuf::from("/home/a/.ssh/work_rsa"))
+            ),
+            (
+                "github".into(),
+                Some(PathBuf::from("/home/a/.ssh/github.com_key"))
+            ),
+            (
+                "legacy".into(),
+                Some(PathBuf::from("/home/a/.ssh/id_ed25519"))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn ssh_config_audit() {
+    let ssh_config = match tealeaves::scan(&rsfs::disk::FS, &"./files/ssh_config").unwrap() {
+        Leaf::SshConfig(_path, ssh_config) => ssh_config,
+        _ => panic!("Expected SshConfig"),
+    };
+    let findings: Vec<(usize, Severity)> = ssh_config
+        .audit()
+        .iter()
+        .map(|finding| (finding.line, finding.severity))
+        .collect();
+    assert_eq!(
+        findings,
+        vec![
+            (3, Severity::High),
+            (7, Severity::Medium),
+            (8, Severity::Medium),
+            (11, Severity::Low),
+            (12, Severity::Low),
+            (17, Severity::Medium),
+        ]
+    );
+    assert_eq!(
+        ssh_config.audit()[0].message,
+        "ForwardAgent yes for all hosts, root on the remote host can use your agent's keys"
+    );
+    assert_eq!(
+        ssh_config.errors,
+        vec![(
+            PathBuf::from("./files/ssh_config"),
+            18,
+            "Unterminated quote".to_string()
+        )]
+    );
+}
+
+#[test]
+fn ssh_config_audit_display() {
+    let fs = memfs();
+    write(
+        &fs,
+        "/home/a/.ssh/config.d/agent.conf",
+        b"Host *\n    ForwardAgent yes\n",
+    );
+    let leaf = tealeaves::scan(&fs, &"/home/a/.ssh/config").unwrap();
+    let output = format!("{}", leaf);
+    assert!(output.starts_with("/home/a/.ssh/config\n\t✓ ssh_config with 9 options"));
+    // Findings in an Include name the included file
+    assert!(output.contains(
+        "\n\t/home/a/.ssh/config.d/agent.conf line 2: 🚨 high: ForwardAgent yes for all hosts"
+    ));
+}